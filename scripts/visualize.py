@@ -23,7 +23,8 @@ def get_max_x(args):
 
 
 def get_values(filename, args):
-    x, y = np.loadtxt(filename, unpack=True)
+    # separator tree files carry the depth as a third column
+    x, y = np.loadtxt(filename, usecols=(0, 1), unpack=True)
     if not args.keep_outliers:
         y = y[x < 10_000_000]
         x = x[x < 10_000_000]
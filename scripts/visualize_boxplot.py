@@ -22,7 +22,8 @@ def get_max_x(files, keep_outliers):
 
 def get_values(filename, args):
     with open(filename, "r") as f:
-        data = [tuple(map(float, line.split())) for line in f if line.strip()]
+        # separator tree files carry the depth as a third column
+        data = [tuple(map(float, line.split()[:2])) for line in f if line.strip()]
     if not args.keep_outliers:
         data = [p for p in data if p[0] < 10_000_000]
     if not data:
@@ -11,7 +11,8 @@ kit_cmap = mcolors.LinearSegmentedColormap.from_list("custom_map", ["white", "#0
 
 
 def get_data(args):
-    x, y = np.loadtxt(args.file, unpack=True)
+    # separator tree files carry the depth as a third column
+    x, y = np.loadtxt(args.file, usecols=(0, 1), unpack=True)
     if args.loglog:
         x = np.log2(x)
         y = np.log2(y)
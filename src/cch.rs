@@ -2,14 +2,19 @@ use hashbrown::{HashMap, HashSet};
 use std::{
     collections::{BTreeSet, VecDeque},
     fs,
+    io::{self, BufWriter, Write},
+    ops::Deref,
     path::Path,
 };
 
+use crate::mmap::CsrGraph;
+use crate::separator::SeparatorReport;
 use crate::{graph::Graph, library, separator};
 
 pub fn compute_separator_sizes_from_order(
     graph: &Graph,
     order: &[usize],
+    policy: &SeparatorTreePolicy,
     out_file: &Path,
 ) -> Vec<(usize, usize)> {
     assert_eq!(graph.get_num_nodes(), order.len());
@@ -18,7 +23,13 @@ pub fn compute_separator_sizes_from_order(
     let tree = chordalize_and_tree(&directed, order, &pos);
     let root = get_root_node(&tree, order);
     let subtree_sizes = get_subtree_sizes(&tree, root);
-    traverse_separator_tree(&tree, root, &subtree_sizes, out_file)
+    to_pairs(&traverse_separator_tree(
+        &tree,
+        root,
+        &subtree_sizes,
+        policy,
+        out_file,
+    ))
 }
 
 pub fn chordalize_and_tree(directed_graph: &Graph, order: &[usize], pos: &[usize]) -> Graph {
@@ -87,51 +98,258 @@ fn get_root_node(tree: &Graph, ord: &[usize]) -> usize {
     unreachable!("Tree does not contain any edges")
 }
 
+// how the elimination tree of an order is read as a hierarchy of separators. every backend
+// goes through the same policy, so (n, s) lists of different orders stay comparable
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparatorTreePolicy {
+    // a child is large if its subtree has more than
+    // max(min_cell_size, child_ratio * subtree size of the parent) nodes, smaller children are
+    // the few nodes a separator cuts off and do not count as cells
+    pub min_cell_size: usize,
+    pub child_ratio: f64,
+    pub chain: ChainRule,
+    pub multiway: MultiwayRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChainRule {
+    // nodes with a single large child are stacked into the separator of the next branching
+    // node below them
+    Accumulate,
+    // the branching node alone is the separator
+    BranchNodeOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MultiwayRule {
+    // one entry for a branching node, however many large children it has
+    Once,
+    // only nodes with exactly two large children are reported, the tree below the others is
+    // still traversed
+    BisectionsOnly,
+}
+
+impl Default for SeparatorTreePolicy {
+    fn default() -> Self {
+        SeparatorTreePolicy {
+            min_cell_size: 10,
+            child_ratio: 0.1,
+            chain: ChainRule::Accumulate,
+            multiway: MultiwayRule::Once,
+        }
+    }
+}
+
+impl SeparatorTreePolicy {
+    pub fn is_large(&self, child_size: usize, parent_size: usize) -> bool {
+        let cutoff = self
+            .min_cell_size
+            .max((self.child_ratio * parent_size as f64) as usize);
+        child_size > cutoff
+    }
+}
+
+// one separator of the hierarchy: n nodes in the cell below the top of its chain, s nodes on the
+// chain and depth separators above it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SeparatorTreeEntry {
+    pub n: usize,
+    pub s: usize,
+    pub depth: usize,
+}
+
 pub fn traverse_separator_tree(
     tree: &Graph,
     root: usize,
     subtree_sizes: &[usize],
+    policy: &SeparatorTreePolicy,
     out_file: &Path,
-) -> Vec<(usize, usize)> {
-    let mut queue = vec![(root, 1)];
+) -> Vec<SeparatorTreeEntry> {
+    let entries = separator_tree_entries(
+        root,
+        |u| tree.get_neighbors(u).iter().copied(),
+        subtree_sizes,
+        policy,
+    );
+    write_separator_tree(&entries, out_file);
+    entries
+}
 
-    let mut res = String::new();
-    let mut res_vec = vec![];
+// an entry for every separator chain, n is the size of the subtree below the top of the chain
+fn separator_tree_entries<I: IntoIterator<Item = usize>>(
+    root: usize,
+    children: impl Fn(usize) -> I,
+    subtree_sizes: &[usize],
+    policy: &SeparatorTreePolicy,
+) -> Vec<SeparatorTreeEntry> {
+    separator_chains(root, children, subtree_sizes, policy)
+        .into_iter()
+        .map(|(chain, depth)| SeparatorTreeEntry {
+            n: subtree_sizes[chain[0]],
+            s: chain.len(),
+            depth,
+        })
+        .collect()
+}
 
-    while let Some((node, separator_size)) = queue.pop() {
-        let cutoff_size = 10.max((0.1 * subtree_sizes[node] as f64) as usize);
-        let large_children = tree
-            .get_neighbors(node)
-            .iter()
-            .filter(|&&child| subtree_sizes[child] > cutoff_size)
+// (chain, depth) for every branching node of the elimination tree: the tree nodes from the top
+// of the chain down to the branching node, or the branching node alone for BranchNodeOnly.
+// children are visited in the order of their ids, the output does not depend on hash set
+// iteration
+fn separator_chains<I: IntoIterator<Item = usize>>(
+    root: usize,
+    children: impl Fn(usize) -> I,
+    subtree_sizes: &[usize],
+    policy: &SeparatorTreePolicy,
+) -> Vec<(Vec<usize>, usize)> {
+    let mut queue = vec![(root, vec![root], 0)];
+    let mut chains = vec![];
+
+    while let Some((node, mut chain, depth)) = queue.pop() {
+        let mut large_children = children(node)
+            .into_iter()
+            .filter(|&child| policy.is_large(subtree_sizes[child], subtree_sizes[node]))
             .collect::<Vec<_>>();
+        large_children.sort_unstable();
 
         match large_children.len() {
             0 => {}
             1 => {
-                for child in large_children {
-                    queue.push((*child, separator_size + 1));
+                let child = large_children[0];
+                match policy.chain {
+                    ChainRule::Accumulate => chain.push(child),
+                    ChainRule::BranchNodeOnly => chain = vec![child],
                 }
+                queue.push((child, chain, depth));
             }
-            _ => {
-                for child in large_children {
-                    queue.push((*child, 1));
+            k => {
+                for &child in large_children.iter().rev() {
+                    queue.push((child, vec![child], depth + 1));
+                }
+                if k == 2 || policy.multiway == MultiwayRule::Once {
+                    chains.push((chain, depth));
                 }
-                res += &format!(
-                    "{} {}\n",
-                    subtree_sizes[node] + separator_size,
-                    separator_size
-                );
-                res_vec.push((subtree_sizes[node] + separator_size, separator_size));
             }
         }
     }
 
-    fs::write(out_file, res);
-    res_vec
+    chains
 }
 
-pub fn get_top_level_separator(g: &Graph, ord: &[usize]) -> Vec<usize> {
+// "n s depth" per line, the first two columns are the (n, s) files of the other backends
+fn write_separator_tree(entries: &[SeparatorTreeEntry], out_file: &Path) {
+    let mut writer = BufWriter::new(fs::File::create(out_file).unwrap());
+    for e in entries {
+        writeln!(writer, "{} {} {}", e.n, e.s, e.depth).unwrap();
+    }
+}
+
+fn to_pairs(entries: &[SeparatorTreeEntry]) -> Vec<(usize, usize)> {
+    entries.iter().map(|e| (e.n, e.s)).collect()
+}
+
+// elimination tree by Liu's algorithm: the parent of v is the root of the union-find set of
+// each earlier neighbor, found with path compression. no fill edges are materialized, n words
+// for parents and ancestors next to the csr arrays. roots have parent usize::MAX
+pub fn elimination_tree<A: Deref<Target = [u32]> + Sync>(
+    csr: &CsrGraph<A>,
+    order: &[usize],
+    pos: &[usize],
+) -> Vec<usize> {
+    let n = csr.get_num_nodes();
+    let mut parent = vec![usize::MAX; n];
+    let mut ancestor = vec![usize::MAX; n];
+    for &v in order {
+        for &u in csr.get_neighbors(v) {
+            let mut r = u as usize;
+            if pos[r] >= pos[v] {
+                continue;
+            }
+            // climb to the root of u, pointing every node on the way to v
+            while ancestor[r] != usize::MAX && ancestor[r] != v {
+                let next = ancestor[r];
+                ancestor[r] = v;
+                r = next;
+            }
+            if ancestor[r] == usize::MAX {
+                ancestor[r] = v;
+                parent[r] = v;
+            }
+        }
+    }
+    parent
+}
+
+// same (n, s) list as compute_separator_sizes_from_order in O(n + m) memory, the graph stays
+// in its (e.g. memory mapped) csr arrays and only the elimination tree is built
+pub fn compute_separator_sizes_from_csr<A: Deref<Target = [u32]> + Sync>(
+    csr: &CsrGraph<A>,
+    order: &[usize],
+    policy: &SeparatorTreePolicy,
+    out_file: &Path,
+) -> Vec<(usize, usize)> {
+    assert_eq!(csr.get_num_nodes(), order.len());
+    let n = order.len();
+    let pos = get_positions_from_order(order);
+    let parent = elimination_tree(csr, order, &pos);
+
+    // children of every node in one array, subtree sizes bottom up along the order
+    let mut first_child = vec![0; n + 1];
+    for &p in &parent {
+        if p != usize::MAX {
+            first_child[p + 1] += 1;
+        }
+    }
+    for i in 0..n {
+        first_child[i + 1] += first_child[i];
+    }
+    let mut children = vec![0; first_child[n]];
+    let mut next = first_child.clone();
+    let mut subtree_sizes = vec![1; n];
+    for &v in order {
+        let p = parent[v];
+        if p != usize::MAX {
+            children[next[p]] = v;
+            next[p] += 1;
+            subtree_sizes[p] += subtree_sizes[v];
+        }
+    }
+
+    let root = *order
+        .iter()
+        .rev()
+        .find(|&&u| first_child[u] < first_child[u + 1])
+        .expect("Tree does not contain any edges");
+    let entries = separator_tree_entries(
+        root,
+        |u| children[first_child[u]..first_child[u + 1]].iter().copied(),
+        &subtree_sizes,
+        policy,
+    );
+    write_separator_tree(&entries, out_file);
+    to_pairs(&entries)
+}
+
+// streams the routingkit directory or graph file from disk
+pub fn compute_separator_sizes_from_dir(
+    dir: &Path,
+    order: &[usize],
+    policy: &SeparatorTreePolicy,
+    out_file: &Path,
+) -> io::Result<Vec<(usize, usize)>> {
+    let csr = CsrGraph::from_file(dir)?;
+    Ok(compute_separator_sizes_from_csr(
+        &csr, order, policy, out_file,
+    ))
+}
+
+// the separator of the first branching node below the root, with the chain above it if the
+// policy accumulates chains
+pub fn get_top_level_separator(
+    g: &Graph,
+    ord: &[usize],
+    policy: &SeparatorTreePolicy,
+) -> Vec<usize> {
     let pos = get_positions_from_order(ord);
     let directed = get_directed_graph(g, &pos);
     let tree = chordalize_and_tree(&directed, ord, &pos);
@@ -143,18 +361,72 @@ pub fn get_top_level_separator(g: &Graph, ord: &[usize]) -> Vec<usize> {
         let large_children = tree
             .get_neighbors(current)
             .iter()
-            .filter(|&&child| subtree_sizes[child] > subtree_sizes[current] / 20)
+            .filter(|&&child| policy.is_large(subtree_sizes[child], subtree_sizes[current]))
             .collect::<Vec<_>>();
 
         if large_children.len() == 1 {
             current = *large_children[0];
             sep.push(current);
         } else {
-            return sep;
+            return match policy.chain {
+                ChainRule::Accumulate => sep,
+                ChainRule::BranchNodeOnly => vec![current],
+            };
         }
     }
 }
 
+// the separators behind the entries of traverse_separator_tree as node sets, in the same
+// order: the chain of tree nodes ending in a branching node, together with all nodes below the
+// top of the chain
+pub fn get_order_separators(
+    graph: &Graph,
+    order: &[usize],
+    policy: &SeparatorTreePolicy,
+) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let pos = get_positions_from_order(order);
+    let directed = get_directed_graph(graph, &pos);
+    let tree = chordalize_and_tree(&directed, order, &pos);
+    let root = get_root_node(&tree, order);
+    let subtree_sizes = get_subtree_sizes(&tree, root);
+
+    let children = |u: usize| tree.get_neighbors(u).iter().copied();
+    separator_chains(root, children, &subtree_sizes, policy)
+        .into_iter()
+        .map(|(chain, _)| {
+            let mut cell = vec![];
+            let mut stack = vec![chain[0]];
+            while let Some(u) = stack.pop() {
+                cell.push(u);
+                stack.extend(children(u));
+            }
+            (chain, cell)
+        })
+        .collect()
+}
+
+pub fn verify_order_separators(
+    graph: &Graph,
+    order: &[usize],
+    policy: &SeparatorTreePolicy,
+    imbalance: f64,
+) -> Vec<SeparatorReport> {
+    get_order_separators(graph, order, policy)
+        .iter()
+        .map(|(separator, cell)| {
+            let index = cell
+                .iter()
+                .enumerate()
+                .map(|(i, &u)| (u, i))
+                .collect::<HashMap<_, _>>();
+            let separator = separator.iter().map(|u| index[u]).collect::<HashSet<_>>();
+            graph
+                .induced_subgraph(cell)
+                .verify_separator(&separator, imbalance)
+        })
+        .collect()
+}
+
 // turns an order into a position array: at index i is the position of node i
 // makes O(1) lookups for the position of a node possible
 pub fn get_positions_from_order(order: &[usize]) -> Vec<usize> {
@@ -179,6 +451,9 @@ pub fn get_directed_graph(graph: &Graph, pos: &[usize]) -> Graph {
 
 #[cfg(test)]
 mod test {
+    use rand::seq::SliceRandom;
+    use rand::thread_rng;
+
     use crate::graph::example::example_c4;
 
     use super::*;
@@ -193,4 +468,205 @@ mod test {
         //assert_eq!(content.lines().nth(1).unwrap(), "1 1");
         //assert_eq!(content.lines().nth(2).unwrap(), "4 2");
     }
+
+    fn nested_dissection_path(nodes: &[usize], order: &mut Vec<usize>) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        nested_dissection_path(&nodes[..mid], order);
+        nested_dissection_path(&nodes[mid + 1..], order);
+        order.push(nodes[mid]);
+    }
+
+    #[test]
+    fn path_order_separators() {
+        let g = Graph::from_edge_list((0..62).map(|i| (i, i + 1)).collect());
+        let mut order = vec![];
+        nested_dissection_path(&(0..63).collect::<Vec<_>>(), &mut order);
+
+        let policy = SeparatorTreePolicy::default();
+        let separators = get_order_separators(&g, &order, &policy);
+        assert_eq!(separators.len(), 3);
+        assert!(separators
+            .iter()
+            .any(|(sep, cell)| sep == &vec![31] && cell.len() == 63));
+
+        let reports = verify_order_separators(&g, &order, &policy, 0.0);
+        assert!(reports
+            .iter()
+            .all(|r| r.is_valid() && r.redundant.is_empty()));
+        assert!(reports.iter().all(|r| r.separator_size == 1));
+    }
+
+    fn separator_tree(
+        g: &Graph,
+        order: &[usize],
+        policy: &SeparatorTreePolicy,
+    ) -> Vec<SeparatorTreeEntry> {
+        let pos = get_positions_from_order(order);
+        let tree = chordalize_and_tree(&get_directed_graph(g, &pos), order, &pos);
+        let root = get_root_node(&tree, order);
+        let subtree_sizes = get_subtree_sizes(&tree, root);
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let entries = traverse_separator_tree(&tree, root, &subtree_sizes, policy, out.path());
+        let written = fs::read_to_string(out.path()).unwrap();
+        for (line, e) in written.lines().zip(&entries) {
+            assert_eq!(line, format!("{} {} {}", e.n, e.s, e.depth));
+        }
+        entries
+    }
+
+    #[test]
+    fn chain_rules() {
+        // a nested dissection path 0..62 below an extra root 62
+        let g = Graph::from_edge_list((0..62).map(|i| (i, i + 1)).collect());
+        let mut order = vec![];
+        nested_dissection_path(&(0..62).collect::<Vec<_>>(), &mut order);
+        order.push(62);
+
+        let policy = SeparatorTreePolicy::default();
+        let entries = separator_tree(&g, &order, &policy);
+        assert_eq!(
+            entries[0],
+            SeparatorTreeEntry {
+                n: 63,
+                s: 2,
+                depth: 0
+            }
+        );
+        assert!(entries[1..].iter().all(|e| e.s == 1 && e.depth == 1));
+        assert_eq!(get_top_level_separator(&g, &order, &policy), vec![62, 31]);
+
+        let policy = SeparatorTreePolicy {
+            chain: ChainRule::BranchNodeOnly,
+            ..Default::default()
+        };
+        let entries = separator_tree(&g, &order, &policy);
+        assert_eq!(
+            entries[0],
+            SeparatorTreeEntry {
+                n: 62,
+                s: 1,
+                depth: 0
+            }
+        );
+        assert_eq!(get_top_level_separator(&g, &order, &policy), vec![31]);
+    }
+
+    #[test]
+    fn entries_match_separators() {
+        // chain_rules with a leaf 63 hanging off the top of the chain, it is part of the cell
+        let mut edges = (0..62).map(|i| (i, i + 1)).collect::<Vec<_>>();
+        edges.push((62, 63));
+        let g = Graph::from_edge_list(edges);
+        let mut order = vec![63];
+        nested_dissection_path(&(0..62).collect::<Vec<_>>(), &mut order);
+        order.push(62);
+
+        for chain in [ChainRule::Accumulate, ChainRule::BranchNodeOnly] {
+            let policy = SeparatorTreePolicy {
+                chain,
+                ..Default::default()
+            };
+            let entries = separator_tree(&g, &order, &policy);
+            let separators = get_order_separators(&g, &order, &policy);
+            assert_eq!(entries.len(), separators.len());
+            for (e, (sep, cell)) in entries.iter().zip(&separators) {
+                assert_eq!((e.n, e.s), (cell.len(), sep.len()));
+            }
+        }
+        let entries = separator_tree(&g, &order, &SeparatorTreePolicy::default());
+        assert_eq!(entries[0].n, 64);
+    }
+
+    #[test]
+    fn multiway_rules() {
+        // three paths of 31 nodes joined at the center 93, which is ordered last
+        let mut edges = vec![];
+        let mut order = vec![];
+        for k in 0..3 {
+            let nodes = (31 * k..31 * (k + 1)).collect::<Vec<_>>();
+            edges.extend(nodes.windows(2).map(|w| (w[0], w[1])));
+            edges.push((nodes[0], 93));
+            nested_dissection_path(&nodes, &mut order);
+        }
+        order.push(93);
+        let g = Graph::from_edge_list(edges);
+
+        let entries = separator_tree(&g, &order, &SeparatorTreePolicy::default());
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries[0],
+            SeparatorTreeEntry {
+                n: 94,
+                s: 1,
+                depth: 0
+            }
+        );
+        assert!(entries[1..].iter().all(|e| e.n == 31 && e.depth == 1));
+
+        let policy = SeparatorTreePolicy {
+            multiway: MultiwayRule::BisectionsOnly,
+            ..Default::default()
+        };
+        let entries = separator_tree(&g, &order, &policy);
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.n == 31 && e.depth == 1));
+        assert_eq!(get_order_separators(&g, &order, &policy).len(), 3);
+
+        // a larger minimum cell size leaves only the split at the center
+        let policy = SeparatorTreePolicy {
+            min_cell_size: 20,
+            ..Default::default()
+        };
+        assert_eq!(separator_tree(&g, &order, &policy).len(), 1);
+    }
+
+    #[test]
+    fn elimination_tree_matches_chordal_tree() {
+        let g = crate::graph::delaunay::delaunay(500).graph;
+        let mut order = g.nodes_iter().collect::<Vec<_>>();
+        order.shuffle(&mut thread_rng());
+        let pos = get_positions_from_order(&order);
+        let tree = chordalize_and_tree(&get_directed_graph(&g, &pos), &order, &pos);
+
+        let dir = tempfile::tempdir().unwrap();
+        g.save_routingkit(dir.path()).unwrap();
+        let parent = elimination_tree(&CsrGraph::open(dir.path()).unwrap(), &order, &pos);
+        for u in g.nodes_iter() {
+            for &child in tree.get_neighbors(u) {
+                assert_eq!(parent[child], u);
+            }
+        }
+        let tree_edges = g.nodes_iter().map(|u| tree.degree(u)).sum::<usize>();
+        assert_eq!(
+            parent.iter().filter(|&&p| p != usize::MAX).count(),
+            tree_edges
+        );
+    }
+
+    #[test]
+    fn streaming_sizes_match() {
+        let g = crate::graph::grid::generate_grid(30);
+        let dir = tempfile::tempdir().unwrap();
+        g.save_routingkit(dir.path()).unwrap();
+        let out = tempfile::NamedTempFile::new().unwrap();
+
+        let mut random = g.nodes_iter().collect::<Vec<_>>();
+        random.shuffle(&mut thread_rng());
+        let mut path_like = vec![];
+        nested_dissection_path(&g.nodes_iter().collect::<Vec<_>>(), &mut path_like);
+        let policy = SeparatorTreePolicy::default();
+        for order in [random, path_like] {
+            let mut expected = compute_separator_sizes_from_order(&g, &order, &policy, out.path());
+            let mut streamed =
+                compute_separator_sizes_from_dir(dir.path(), &order, &policy, out.path()).unwrap();
+            expected.sort_unstable();
+            streamed.sort_unstable();
+            assert_eq!(streamed, expected);
+            let written = fs::read_to_string(out.path()).unwrap();
+            assert_eq!(written.lines().count(), streamed.len());
+        }
+    }
 }
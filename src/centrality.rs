@@ -0,0 +1,209 @@
+use chrono::Local;
+use hashbrown::{DefaultHashBuilder, HashSet};
+use ordered_float::OrderedFloat;
+use priority_queue::PriorityQueue;
+use rand::seq::index;
+use rand::thread_rng;
+use rayon::prelude::*;
+
+use std::cmp::Reverse;
+
+use crate::cch::{get_top_level_separator, SeparatorTreePolicy};
+use crate::dataset::Config;
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::library;
+use crate::separator::get_ord;
+
+#[derive(Debug, Clone)]
+pub struct Centrality {
+    // shortest paths between unordered pairs through a node, extrapolated from the pivots
+    pub betweenness: Vec<f64>,
+    // lower bound on the reach, max over the pivot trees of min(d(s, v), height of v)
+    pub reach: Vec<f64>,
+    pub pivots: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeparatorOverlap {
+    pub separator_size: usize,
+    // share of the separator among the separator_size nodes of highest betweenness
+    pub betweenness: f64,
+    pub reach: f64,
+    // expected share for a random node set of the same size
+    pub random: f64,
+}
+
+impl GeometricGraph {
+    // one dijkstra from s: dependencies of s on every node (Brandes 2001) and the reach of
+    // every node in the shortest path tree of s
+    fn accumulate_centrality(&self, s: usize, betweenness: &mut [f64], reach: &mut [f64]) {
+        let n = self.graph.get_num_nodes();
+        let mut distance = vec![f64::INFINITY; n];
+        let mut paths = vec![0.0; n];
+        let mut predecessors = vec![vec![]; n];
+        let mut settled = vec![];
+        distance[s] = 0.0;
+        paths[s] = 1.0;
+        let mut pq = PriorityQueue::<_, _, DefaultHashBuilder>::with_default_hasher();
+        pq.push(s, Reverse(OrderedFloat(0.0)));
+
+        while let Some((u, Reverse(OrderedFloat(u_dist)))) = pq.pop() {
+            settled.push(u);
+            for &v in self.graph.get_neighbors(u) {
+                let v_dist = u_dist + self.euclidean_distance(u, v);
+                if v_dist < distance[v] {
+                    distance[v] = v_dist;
+                    paths[v] = paths[u];
+                    predecessors[v] = vec![u];
+                    pq.push_increase(v, Reverse(OrderedFloat(v_dist)));
+                } else if v_dist == distance[v] {
+                    paths[v] += paths[u];
+                    predecessors[v].push(u);
+                }
+            }
+        }
+
+        let mut dependency = vec![0.0; n];
+        let mut height = vec![0.0; n];
+        for &w in settled.iter().rev() {
+            for &v in &predecessors[w] {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                let up = height[w] + distance[w] - distance[v];
+                height[v] = f64::max(height[v], up);
+            }
+            if w != s {
+                betweenness[w] += dependency[w];
+            }
+            reach[w] = f64::max(reach[w], f64::min(distance[w], height[w]));
+        }
+    }
+
+    // brandes with pivots, all nodes are sources if pivots >= n
+    pub fn centrality(&self, pivots: usize) -> Centrality {
+        let n = self.graph.get_num_nodes();
+        let pivots = pivots.min(n);
+        let sources = index::sample(&mut thread_rng(), n, pivots).into_vec();
+
+        let (betweenness, reach) = sources
+            .into_par_iter()
+            .fold(
+                || (vec![0.0; n], vec![0.0; n]),
+                |(mut b, mut r), s| {
+                    self.accumulate_centrality(s, &mut b, &mut r);
+                    (b, r)
+                },
+            )
+            .reduce(
+                || (vec![0.0; n], vec![0.0; n]),
+                |(b1, r1), (b2, r2)| {
+                    let r = r1.iter().zip(&r2).map(|(&x, &y)| f64::max(x, y)).collect();
+                    (library::add_vecs(&b1, &b2), r)
+                },
+            );
+
+        // every unordered pair is counted from both ends when all nodes are pivots
+        let scale = n as f64 / pivots.max(1) as f64 / 2.0;
+        Centrality {
+            betweenness: betweenness.into_iter().map(|b| b * scale).collect(),
+            reach,
+            pivots,
+        }
+    }
+
+    // overlap of the top level separator of the inertial flowcutter order with the nodes of
+    // highest betweenness and reach. the order is stored as output/ord/{name}
+    pub fn highway_separator_overlap(&self, name: &str, pivots: usize) -> SeparatorOverlap {
+        let g_path = Config::get().output("graphs").join(name);
+        self.save_routingkit(&g_path).unwrap();
+        let ord = get_ord(&g_path, Some(name));
+        let separator = get_top_level_separator(&self.graph, &ord, &SeparatorTreePolicy::default());
+
+        let centrality = self.centrality(pivots);
+        let overlap = centrality.separator_overlap(&separator);
+        println!("{}\t{}: {:?}", Local::now(), name, overlap);
+        overlap
+    }
+}
+
+impl Centrality {
+    // indices of the k largest values
+    fn top(values: &[f64], k: usize) -> HashSet<usize> {
+        let mut nodes = (0..values.len()).collect::<Vec<_>>();
+        nodes.sort_unstable_by_key(|&u| Reverse(OrderedFloat(values[u])));
+        nodes.into_iter().take(k).collect()
+    }
+
+    pub fn separator_overlap(&self, separator: &[usize]) -> SeparatorOverlap {
+        let k = separator.len();
+        let share = |values: &[f64]| {
+            let top = Self::top(values, k);
+            separator.iter().filter(|u| top.contains(*u)).count() as f64 / k.max(1) as f64
+        };
+        SeparatorOverlap {
+            separator_size: k,
+            betweenness: share(&self.betweenness),
+            reach: share(&self.reach),
+            random: k as f64 / self.betweenness.len().max(1) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geo::Point;
+
+    use crate::graph::example::geometric_path;
+    use crate::graph::Graph;
+
+    // nested dissection order of the nodes lo..hi of a path, middles last
+    fn path_order(lo: usize, hi: usize, ord: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        path_order(lo, mid, ord);
+        path_order(mid + 1, hi, ord);
+        ord.push(mid);
+    }
+
+    #[test]
+    fn exact_on_path() {
+        let n = 21;
+        let c = geometric_path(n).centrality(n);
+        for i in 0..n {
+            assert_eq!(c.betweenness[i], (i * (n - 1 - i)) as f64);
+            assert_eq!(c.reach[i], i.min(n - 1 - i) as f64);
+        }
+    }
+
+    #[test]
+    fn shortest_paths_are_split() {
+        // two equally long routes from 0 to 3
+        let g = GeometricGraph::new(
+            Graph::from_edge_list(vec![(0, 1), (1, 3), (0, 2), (2, 3)]),
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(1.0, 0.0),
+                Point::new(0.0, 1.0),
+                Point::new(1.0, 1.0),
+            ],
+        );
+        let c = g.centrality(4);
+        assert_eq!(c.betweenness, vec![0.5; 4]);
+    }
+
+    #[test]
+    fn separator_is_central_on_path() {
+        let g = geometric_path(31);
+        let mut ord = vec![];
+        path_order(0, 31, &mut ord);
+        let separator = get_top_level_separator(&g.graph, &ord, &SeparatorTreePolicy::default());
+        assert_eq!(separator, vec![15]);
+
+        let overlap = g.centrality(31).separator_overlap(&separator);
+        assert_eq!(overlap.betweenness, 1.0);
+        assert_eq!(overlap.reach, 1.0);
+        assert!(overlap.random < 0.1);
+    }
+}
@@ -0,0 +1,259 @@
+use chrono::Local;
+use geo::Point;
+use rand::seq::index;
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+
+use std::f64::consts::PI;
+
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::voronoi::prune_graph_spanner;
+
+// (straight line distance, network distance) of one sampled pair
+pub type CircuityPair = (f64, f64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuityBand {
+    // straight line distances [lower, upper)
+    pub lower: f64,
+    pub upper: f64,
+    pub pairs: usize,
+    pub mean: f64,
+    pub median: f64,
+}
+
+impl GeometricGraph {
+    // pairs from one dijkstra per source to random targets, the straight line distances follow
+    // the distance distribution of the graph, so long pairs dominate
+    pub fn random_circuity_pairs(&self, sources: usize, targets: usize) -> Vec<CircuityPair> {
+        let n = self.graph.get_num_nodes();
+        index::sample(&mut thread_rng(), n, sources.min(n))
+            .into_vec()
+            .into_par_iter()
+            .flat_map_iter(|s| {
+                let distances = self.dijkstra_one_to_all(s);
+                let mut rng = thread_rng();
+                (0..targets)
+                    .map(|_| rng.gen_range(0..n))
+                    .filter(|&t| distances[t].is_finite() && self.euclidean_distance(s, t) > 0.0)
+                    .map(|t| (self.euclidean_distance(s, t), distances[t]))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn mean_circuity(&self, sources: usize, targets: usize) -> f64 {
+        let pairs = self.random_circuity_pairs(sources, targets);
+        pairs.iter().map(|&(e, d)| d / e).sum::<f64>() / pairs.len().max(1) as f64
+    }
+
+    // about pairs_per_band pairs with a straight line distance in each band. the target is the
+    // node nearest to a random point in the annulus around the source, kept if it falls into
+    // the band, gives up on a band after 20 tries per pair. empty bands get no pairs
+    pub fn banded_circuity_pairs(
+        &self,
+        band_edges: &[f64],
+        pairs_per_band: usize,
+    ) -> Vec<CircuityPair> {
+        let n = self.graph.get_num_nodes();
+        let rtree = RTree::bulk_load(
+            self.positions
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| GeomWithData::new(p, i))
+                .collect::<Vec<_>>(),
+        );
+
+        band_edges
+            .windows(2)
+            .flat_map(|band| {
+                let (lower, upper) = (band[0], band[1]);
+                let mut rng = thread_rng();
+                let mut pairs = vec![];
+                if n == 0 || lower >= upper {
+                    return pairs;
+                }
+                for _ in 0..20 * pairs_per_band {
+                    if pairs.len() == pairs_per_band {
+                        break;
+                    }
+                    let s = rng.gen_range(0..n);
+                    let p = self.get_position(s);
+                    let r = rng.gen_range(lower..upper);
+                    let angle = rng.gen_range(0.0..2.0 * PI);
+                    let q = Point::new(p.x() + r * angle.cos(), p.y() + r * angle.sin());
+                    let t = rtree.nearest_neighbor(&q).unwrap().data;
+                    let e = self.euclidean_distance(s, t);
+                    if e >= lower && e < upper {
+                        pairs.push((s, t, e));
+                    }
+                }
+                pairs
+            })
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(s, t, e)| (e, self.dijsktra_one_to_one(s, t)))
+            .filter(|&(_, d)| d.is_finite())
+            .collect()
+    }
+
+    // doubling bands starting at the mean edge length, so graphs with different units share
+    // the same bands relative to their edges
+    pub fn circuity_band_edges(&self, bands: usize) -> Vec<f64> {
+        let lengths = self.get_edge_lengths();
+        let mean = lengths.values().sum::<f64>() / lengths.len().max(1) as f64;
+        (0..=bands).map(|i| mean * 2f64.powi(i as i32)).collect()
+    }
+}
+
+// circuity (network over straight line distance) of the pairs per straight line distance band,
+// pairs outside all bands are dropped
+pub fn circuity_histogram(pairs: &[CircuityPair], band_edges: &[f64]) -> Vec<CircuityBand> {
+    band_edges
+        .windows(2)
+        .map(|band| {
+            let mut ratios = pairs
+                .iter()
+                .filter(|&&(e, _)| e >= band[0] && e < band[1])
+                .map(|&(e, d)| d / e)
+                .collect::<Vec<_>>();
+            ratios.sort_unstable_by(f64::total_cmp);
+            let (mean, median) = match ratios.len() {
+                0 => (f64::NAN, f64::NAN),
+                len => (ratios.iter().sum::<f64>() / len as f64, ratios[len / 2]),
+            };
+            CircuityBand {
+                lower: band[0],
+                upper: band[1],
+                pairs: ratios.len(),
+                mean,
+                median,
+            }
+        })
+        .collect()
+}
+
+// mean absolute difference of the band means, bands empty on either side are skipped
+pub fn circuity_error(bands: &[CircuityBand], reference: &[CircuityBand]) -> f64 {
+    let diffs = bands
+        .iter()
+        .zip(reference)
+        .filter(|(a, b)| a.pairs > 0 && b.pairs > 0)
+        .map(|(a, b)| (a.mean - b.mean).abs())
+        .collect::<Vec<_>>();
+    diffs.iter().sum::<f64>() / diffs.len().max(1) as f64
+}
+
+// error of prune_graph_spanner(base, t) against the banded circuity of the reference for every
+// t, the reference bands are taken relative to the mean edge length of both graphs
+pub fn calibrate_spanner(
+    base: &GeometricGraph,
+    reference: &GeometricGraph,
+    ts: &[f64],
+    bands: usize,
+    pairs_per_band: usize,
+) -> Vec<(f64, f64)> {
+    let reference_edges = reference.circuity_band_edges(bands);
+    let reference_bands = circuity_histogram(
+        &reference.banded_circuity_pairs(&reference_edges, pairs_per_band),
+        &reference_edges,
+    );
+
+    ts.iter()
+        .map(|&t| {
+            let mut g = base.clone();
+            prune_graph_spanner(&mut g, t);
+            let edges = g.circuity_band_edges(bands);
+            let bands =
+                circuity_histogram(&g.banded_circuity_pairs(&edges, pairs_per_band), &edges);
+            let error = circuity_error(&bands, &reference_bands);
+            println!(
+                "{}\tt = {:.2}: circuity error {:.4}",
+                Local::now(),
+                t,
+                error
+            );
+            (t, error)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::graph::{delaunay, example};
+
+    // mean circuity from the first sources nodes to all others, the same pairs on every graph
+    // with these positions
+    fn circuity_from(g: &GeometricGraph, sources: usize) -> f64 {
+        let pairs = (0..sources)
+            .flat_map(|s| {
+                let distances = g.dijkstra_one_to_all(s);
+                (0..g.graph.get_num_nodes())
+                    .filter(|&t| t != s)
+                    .map(|t| distances[t] / g.euclidean_distance(s, t))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        pairs.iter().sum::<f64>() / pairs.len() as f64
+    }
+
+    #[test]
+    fn straight_path_has_circuity_one() {
+        let g = example::geometric_path(50);
+        assert!((g.mean_circuity(10, 10) - 1.0).abs() < 1e-9);
+
+        let edges = g.circuity_band_edges(4);
+        assert_eq!(edges, vec![1.0, 2.0, 4.0, 8.0, 16.0]);
+        let bands = circuity_histogram(&g.banded_circuity_pairs(&edges, 20), &edges);
+        assert_eq!(bands.len(), 4);
+        for band in bands {
+            assert!(band.pairs > 0);
+            assert!((band.mean - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn edgeless_graph_has_no_pairs() {
+        let g = GeometricGraph::new(
+            crate::graph::Graph::with_node_count(3),
+            vec![Point::new(0.0, 0.0); 3],
+        );
+        let edges = g.circuity_band_edges(3);
+        assert!(g.banded_circuity_pairs(&edges, 10).is_empty());
+    }
+
+    #[test]
+    fn histogram_by_band() {
+        let pairs = [(1.0, 2.0), (1.5, 1.5), (3.0, 3.3), (10.0, 11.0)];
+        let bands = circuity_histogram(&pairs, &[1.0, 2.0, 4.0]);
+        assert_eq!(bands[0].pairs, 2);
+        assert!((bands[0].mean - 1.5).abs() < 1e-12);
+        assert_eq!(bands[0].median, 2.0);
+        assert_eq!(bands[1].pairs, 1);
+        assert!((bands[1].mean - 1.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spanner_raises_circuity() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let points = (0..500)
+            .map(|_| Point::new(rng.gen(), rng.gen()))
+            .collect::<Vec<_>>();
+        let g = delaunay::delaunay_points(&points);
+        let mut sparse = g.clone();
+        prune_graph_spanner(&mut sparse, 3.0);
+        assert!(sparse.graph.get_num_edges() < g.graph.get_num_edges());
+        assert!(circuity_from(&sparse, 20) >= circuity_from(&g, 20));
+
+        // the graph itself is the best match for t close to 1
+        let res = calibrate_spanner(&g, &g, &[1.0, 3.0], 4, 50);
+        assert!(res[0].1 <= res[1].1);
+    }
+}
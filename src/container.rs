@@ -0,0 +1,393 @@
+use geo::Point;
+use hashbrown::HashMap;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde_json::{json, Value};
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::graph::projection::Crs;
+use crate::graph::Graph;
+use crate::mmap::{self, CsrGraph, MappedSlice, Pod};
+
+// layout of a graph file, all integers little endian:
+//   header   magic, version, crs, #nodes, #arcs, #sections, padding
+//   table    per section: kind, padding, offset, length in bytes
+//   data     the sections, each starting at a multiple of 8 so they can be mapped in place
+const MAGIC: &[u8; 8] = b"RNSGRAPH";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 40;
+const ENTRY_SIZE: usize = 24;
+const ALIGNMENT: usize = 8;
+
+const FIRST_OUT: u32 = 1;
+const HEAD: u32 = 2;
+const X: u32 = 3;
+const Y: u32 = 4;
+const WEIGHT: u32 = 5;
+const ROAD_CLASS: u32 = 6;
+const ORDER: u32 = 7;
+const METADATA: u32 = 8;
+
+// optional data stored next to the graph, edge attributes are keyed by arc like get_edge_lengths
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    pub crs: Option<Crs>,
+    pub weights: Option<HashMap<(usize, usize), f64>>,
+    pub road_class: Option<HashMap<(usize, usize), u8>>,
+    // position of every node in a nested dissection order
+    pub order: Option<Vec<usize>>,
+    // provenance, e.g. generator, parameters, seed and applied contractions
+    pub metadata: Value,
+}
+
+pub struct GraphFile {
+    pub version: u32,
+    pub crs: Option<Crs>,
+    pub csr: CsrGraph,
+    pub x: Option<MappedSlice<f64>>,
+    pub y: Option<MappedSlice<f64>>,
+    pub weights: Option<MappedSlice<f64>>,
+    pub road_class: Option<MappedSlice<u8>>,
+    pub order: Option<MappedSlice<u32>>,
+    pub metadata: Value,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// value of every arc, fails if the map misses one
+fn arc_values<T: Copy>(
+    arcs: &[(usize, usize)],
+    values: &HashMap<(usize, usize), T>,
+    name: &str,
+) -> io::Result<Vec<T>> {
+    arcs.iter()
+        .map(|e| {
+            values
+                .get(e)
+                .copied()
+                .ok_or_else(|| invalid_input(&format!("{name} missing for arc {e:?}")))
+        })
+        .collect()
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> usize {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize
+}
+
+fn as_bytes<T: Pod>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+fn crs_to_u32(crs: Option<Crs>) -> u32 {
+    match crs {
+        None => 0,
+        Some(Crs::Wgs84) => 1,
+        Some(Crs::Planar) => 2,
+    }
+}
+
+fn crs_from_u32(crs: u32) -> io::Result<Option<Crs>> {
+    match crs {
+        0 => Ok(None),
+        1 => Ok(Some(Crs::Wgs84)),
+        2 => Ok(Some(Crs::Planar)),
+        _ => Err(invalid_data("unknown coordinate system")),
+    }
+}
+
+pub fn is_graph_file(file: &Path) -> bool {
+    let mut magic = [0; 8];
+    File::open(file)
+        .and_then(|mut f| io::Read::read_exact(&mut f, &mut magic))
+        .is_ok_and(|_| &magic == MAGIC)
+}
+
+pub fn write(
+    file: &Path,
+    graph: &Graph,
+    positions: Option<&[Point]>,
+    attributes: &Attributes,
+) -> io::Result<()> {
+    let n = graph.get_num_nodes();
+    let neighbors = graph
+        .data
+        .par_iter()
+        .map(|neighbors| {
+            let mut neighbors = neighbors.iter().map(|&v| v as u32).collect::<Vec<_>>();
+            neighbors.sort_unstable();
+            neighbors
+        })
+        .collect::<Vec<_>>();
+    let mut first_out = Vec::with_capacity(n + 1);
+    first_out.push(0u32);
+    for neighbors in &neighbors {
+        first_out.push(first_out.last().unwrap() + neighbors.len() as u32);
+    }
+    let head = neighbors.concat();
+    let arcs = (0..n)
+        .flat_map(|u| neighbors[u].iter().map(move |&v| (u, v as usize)))
+        .collect::<Vec<_>>();
+
+    let mut sections: Vec<(u32, Vec<u8>)> = vec![
+        (FIRST_OUT, as_bytes(&first_out).to_vec()),
+        (HEAD, as_bytes(&head).to_vec()),
+    ];
+    if let Some(positions) = positions {
+        if positions.len() != n {
+            return Err(invalid_input("one position per node expected"));
+        }
+        let x = positions.iter().map(|p| p.x()).collect::<Vec<_>>();
+        let y = positions.iter().map(|p| p.y()).collect::<Vec<_>>();
+        sections.push((X, as_bytes(&x).to_vec()));
+        sections.push((Y, as_bytes(&y).to_vec()));
+    }
+    if let Some(weights) = &attributes.weights {
+        let w = arc_values(&arcs, weights, "weight")?;
+        sections.push((WEIGHT, as_bytes(&w).to_vec()));
+    }
+    if let Some(road_class) = &attributes.road_class {
+        sections.push((ROAD_CLASS, arc_values(&arcs, road_class, "road class")?));
+    }
+    if let Some(order) = &attributes.order {
+        if order.len() != n {
+            return Err(invalid_input("one order position per node expected"));
+        }
+        let order = order.iter().map(|&o| o as u32).collect::<Vec<_>>();
+        sections.push((ORDER, as_bytes(&order).to_vec()));
+    }
+    let mut metadata = attributes.metadata.clone();
+    if metadata.is_null() {
+        metadata = json!({});
+    }
+    if let Some(m) = metadata.as_object_mut() {
+        m.entry("created")
+            .or_insert_with(|| json!(chrono::Local::now().to_rfc3339()));
+    }
+    sections.push((METADATA, serde_json::to_vec(&metadata)?));
+
+    let mut table = Vec::new();
+    let mut offset = HEADER_SIZE + ENTRY_SIZE * sections.len();
+    for (kind, bytes) in &sections {
+        offset = offset.next_multiple_of(ALIGNMENT);
+        table.push((*kind, offset, bytes.len()));
+        offset += bytes.len();
+    }
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut w = BufWriter::new(File::create(file)?);
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&crs_to_u32(attributes.crs).to_le_bytes())?;
+    w.write_all(&(n as u64).to_le_bytes())?;
+    w.write_all(&(head.len() as u64).to_le_bytes())?;
+    w.write_all(&(sections.len() as u32).to_le_bytes())?;
+    w.write_all(&[0; 4])?;
+    for &(kind, offset, len) in &table {
+        w.write_all(&kind.to_le_bytes())?;
+        w.write_all(&[0; 4])?;
+        w.write_all(&(offset as u64).to_le_bytes())?;
+        w.write_all(&(len as u64).to_le_bytes())?;
+    }
+    let mut position = HEADER_SIZE + ENTRY_SIZE * sections.len();
+    for ((_, bytes), &(_, offset, _)) in sections.iter().zip(&table) {
+        w.write_all(&vec![0; offset - position])?;
+        w.write_all(bytes)?;
+        position = offset + bytes.len();
+    }
+    w.flush()
+}
+
+impl GraphFile {
+    pub fn open(file: &Path) -> io::Result<Self> {
+        let map = mmap::map_file(file)?.ok_or_else(|| invalid_data("empty graph file"))?;
+        let bytes: &[u8] = &map;
+        if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+            return Err(invalid_data("not a graph file"));
+        }
+        let version = read_u32(bytes, 8);
+        if version > VERSION {
+            return Err(invalid_data("graph file written by a newer version"));
+        }
+        let crs = crs_from_u32(read_u32(bytes, 12))?;
+        let (n, m) = (read_u64(bytes, 16), read_u64(bytes, 24));
+        let num_sections = read_u32(bytes, 32) as usize;
+        if bytes.len() < HEADER_SIZE + ENTRY_SIZE * num_sections {
+            return Err(invalid_data("truncated section table"));
+        }
+
+        let mut sections = HashMap::new();
+        for i in 0..num_sections {
+            let at = HEADER_SIZE + ENTRY_SIZE * i;
+            // unknown kinds are skipped so older readers can open newer files
+            sections.insert(
+                read_u32(bytes, at),
+                (read_u64(bytes, at + 8), read_u64(bytes, at + 16)),
+            );
+        }
+        let section = |kind: u32| sections.get(&kind).copied();
+        fn slice<T: Pod>(
+            map: &Arc<Mmap>,
+            range: Option<(usize, usize)>,
+            len: usize,
+        ) -> io::Result<Option<MappedSlice<T>>> {
+            let Some((offset, bytes)) = range else {
+                return Ok(None);
+            };
+            let s = MappedSlice::new(Some(map.clone()), offset, bytes)?;
+            if s.len() != len {
+                return Err(invalid_data("section has the wrong length"));
+            }
+            Ok(Some(s))
+        }
+
+        let first_out = slice(&map, section(FIRST_OUT), n + 1)?
+            .ok_or_else(|| invalid_data("missing first_out"))?;
+        let head = slice(&map, section(HEAD), m)?.ok_or_else(|| invalid_data("missing head"))?;
+        let metadata = match section(METADATA) {
+            Some((offset, len)) => serde_json::from_slice(
+                bytes
+                    .get(offset..offset + len)
+                    .ok_or_else(|| invalid_data("truncated metadata"))?,
+            )?,
+            None => Value::Null,
+        };
+
+        Ok(GraphFile {
+            version,
+            crs,
+            csr: CsrGraph::new(first_out, head)?,
+            x: slice(&map, section(X), n)?,
+            y: slice(&map, section(Y), n)?,
+            weights: slice(&map, section(WEIGHT), m)?,
+            road_class: slice(&map, section(ROAD_CLASS), m)?,
+            order: slice(&map, section(ORDER), n)?,
+            metadata,
+        })
+    }
+
+    pub fn get_positions(&self) -> Option<Vec<Point>> {
+        let (x, y) = (self.x.as_ref()?, self.y.as_ref()?);
+        Some(
+            x.par_iter()
+                .zip(y.par_iter())
+                .map(|(&x, &y)| Point::new(x, y))
+                .collect(),
+        )
+    }
+
+    // values stored per arc in csr order keyed by (tail, head)
+    pub fn get_arc_map<T: Pod>(&self, values: &[T]) -> HashMap<(usize, usize), T> {
+        (0..self.csr.get_num_nodes())
+            .into_par_iter()
+            .flat_map_iter(|u| {
+                let start = self.csr.first_out[u] as usize;
+                self.csr
+                    .get_neighbors(u)
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, &v)| ((u, v as usize), values[start + i]))
+            })
+            .collect()
+    }
+
+    pub fn get_attributes(&self) -> Attributes {
+        Attributes {
+            crs: self.crs,
+            weights: self.weights.as_ref().map(|w| self.get_arc_map(w)),
+            road_class: self.road_class.as_ref().map(|r| self.get_arc_map(r)),
+            order: self
+                .order
+                .as_ref()
+                .map(|o| o.iter().map(|&i| i as usize).collect()),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::example;
+    use crate::graph::geometric_graph::GeometricGraph;
+
+    #[test]
+    fn roundtrip() {
+        let g = example::example1();
+        let attributes = Attributes {
+            crs: Some(Crs::Planar),
+            weights: Some(g.get_edge_lengths()),
+            road_class: Some(g.graph.get_edges().into_iter().map(|e| (e, 3)).collect()),
+            order: Some((0..9).rev().collect()),
+            metadata: json!({"generator": "example1", "seed": 42}),
+        };
+        let file = tempfile::NamedTempFile::new().unwrap();
+        g.save_with_attributes(file.path(), &attributes).unwrap();
+        assert!(is_graph_file(file.path()));
+
+        let loaded = GraphFile::open(file.path()).unwrap();
+        assert_eq!(loaded.crs, Some(Crs::Planar));
+        assert_eq!(loaded.csr.to_graph().data, g.graph.data);
+        assert_eq!(loaded.get_positions().unwrap(), g.positions);
+
+        let loaded = loaded.get_attributes();
+        assert_eq!(loaded.weights, attributes.weights);
+        assert_eq!(loaded.road_class, attributes.road_class);
+        assert_eq!(loaded.order, attributes.order);
+        assert_eq!(loaded.metadata["seed"], 42);
+        assert!(loaded.metadata["created"].is_string());
+    }
+
+    #[test]
+    fn routingkit_still_readable() {
+        let g = example::example_c4();
+        let dir = tempfile::tempdir().unwrap();
+        g.save_routingkit(dir.path()).unwrap();
+        assert_eq!(Graph::from_file(dir.path()).unwrap().data, g.graph.data);
+
+        let file = dir.path().join("c4.graph");
+        g.graph.save(&file).unwrap();
+        assert_eq!(Graph::from_file(&file).unwrap().data, g.graph.data);
+        assert!(GeometricGraph::from_file(&file).is_err());
+
+        let mut planar = g.clone();
+        planar.crs = Some(Crs::Planar);
+        planar.save(&file).unwrap();
+        assert_eq!(GraphFile::open(&file).unwrap().crs, Some(Crs::Planar));
+        assert_eq!(
+            GeometricGraph::from_file(&file).unwrap().crs,
+            Some(Crs::Planar)
+        );
+    }
+
+    #[test]
+    fn incomplete_attributes() {
+        let g = example::example_c4();
+        let mut weights = g.get_edge_lengths();
+        weights.remove(&(0, 1));
+        let attributes = Attributes {
+            weights: Some(weights),
+            ..Default::default()
+        };
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let err = g
+            .save_with_attributes(file.path(), &attributes)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
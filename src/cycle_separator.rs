@@ -0,0 +1,444 @@
+use chrono::Local;
+use hashbrown::{HashMap, HashSet};
+
+use std::collections::VecDeque;
+use std::fs;
+
+use crate::dataset::Config;
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::planar::planarize;
+use crate::graph::planarity::Embedding;
+use crate::graph::{delaunay, gabriel_graph, noise, relative_neighborhood, Graph};
+use crate::lca::LcaUtil;
+use crate::separator::Mode;
+
+#[derive(Debug, Clone)]
+pub struct PlanarSeparator {
+    pub separator: HashSet<usize>,
+    // nodes on the two sides, every component left by the separator lies on one of them
+    pub inside: usize,
+    pub outside: usize,
+    // a bfs level instead of a fundamental cycle
+    pub from_level: bool,
+}
+
+// the embedding with every face that is not a triangle split by a dummy node connected to all
+// positions of its boundary walk, edges are kept by id as a dummy can attach twice to one node
+struct Triangulation {
+    // nodes from n on are dummies
+    n: usize,
+    num_nodes: usize,
+    edges: Vec<(usize, usize)>,
+    // the two triangles on either side of every edge
+    edge_triangles: Vec<[usize; 2]>,
+    num_triangles: usize,
+    // the first triangle of every dummy, it carries the dummy when counting nodes inside cycles
+    dummy_triangle: HashMap<usize, usize>,
+}
+
+impl Triangulation {
+    fn new(embedding: &Embedding) -> Triangulation {
+        let n = embedding.rotation.len();
+        let mut t = Triangulation {
+            n,
+            num_nodes: n,
+            edges: vec![],
+            edge_triangles: vec![],
+            num_triangles: 0,
+            dummy_triangle: HashMap::new(),
+        };
+
+        let mut dart_triangle = HashMap::new();
+        for face in embedding.faces() {
+            let k = face.len();
+            if k == 3 && face[0] != face[1] && face[1] != face[2] && face[0] != face[2] {
+                for i in 0..3 {
+                    dart_triangle.insert((face[i], face[(i + 1) % 3]), t.num_triangles);
+                }
+                t.num_triangles += 1;
+                continue;
+            }
+
+            let d = t.num_nodes;
+            t.num_nodes += 1;
+            let first = t.num_triangles;
+            t.num_triangles += k;
+            t.dummy_triangle.insert(d, first);
+            for i in 0..k {
+                dart_triangle.insert((face[i], face[(i + 1) % k]), first + i);
+                t.edges.push((d, face[i]));
+                t.edge_triangles.push([first + (i + k - 1) % k, first + i]);
+            }
+        }
+
+        for (u, neighbors) in embedding.rotation.iter().enumerate() {
+            for &v in neighbors.iter().filter(|&&v| u < v) {
+                t.edges.push((u, v));
+                t.edge_triangles
+                    .push([dart_triangle[&(u, v)], dart_triangle[&(v, u)]]);
+            }
+        }
+        t
+    }
+
+    fn is_dummy(&self, u: usize) -> bool {
+        u >= self.n
+    }
+}
+
+// node in the middle of a bfs path between two far apart nodes, keeps the bfs tree shallow
+fn central_node(g: &Graph) -> usize {
+    let far = |s: usize| {
+        let d = g.bfs(s);
+        let u = (0..d.len()).max_by_key(|&u| d[u]).unwrap();
+        (u, d)
+    };
+    let (a, _) = far(0);
+    let (b, from_a) = far(a);
+    let mut u = b;
+    for _ in 0..from_a[b] / 2 {
+        u = *g
+            .get_neighbors(u)
+            .iter()
+            .find(|&&v| from_a[v] + 1 == from_a[u])
+            .unwrap();
+    }
+    u
+}
+
+impl Graph {
+    // fundamental cycle separator (Lipton and Tarjan 1979) of a connected graph with a planar
+    // embedding. every non-tree edge of a bfs tree in the triangulated graph closes a cycle,
+    // the number of nodes inside follows from euler's formula and the triangles on one side
+    // in the dual tree. bfs levels compete as in the first phase of lipton tarjan. the
+    // smallest separator within the balance wins, otherwise the most balanced one
+    pub fn fundamental_cycle_separator(
+        &self,
+        embedding: &Embedding,
+        imbalance: f64,
+    ) -> PlanarSeparator {
+        let n = self.get_num_nodes();
+        // too small to have a triangle, one of two nodes separates them
+        if n <= 2 {
+            return PlanarSeparator {
+                separator: (0..n / 2).collect(),
+                inside: 0,
+                outside: n - n / 2,
+                from_level: false,
+            };
+        }
+        assert!(
+            self.is_connected(),
+            "cycle separators need a connected graph"
+        );
+        let max_side = ((1.0 + imbalance) * n.div_ceil(2) as f64) as usize;
+        let t = Triangulation::new(embedding);
+
+        // bfs tree on the original nodes, dummies hang below their shallowest face node
+        let root = central_node(self);
+        let mut depth = vec![usize::MAX; t.num_nodes];
+        let mut parent = vec![usize::MAX; t.num_nodes];
+        let mut tree_edge = vec![false; t.edges.len()];
+        let mut levels = vec![];
+        depth[root] = 0;
+        let mut queue = VecDeque::from([root]);
+        while let Some(u) = queue.pop_front() {
+            if levels.len() == depth[u] {
+                levels.push(0);
+            }
+            levels[depth[u]] += 1;
+            for &v in self.get_neighbors(u) {
+                if depth[v] == usize::MAX {
+                    depth[v] = depth[u] + 1;
+                    parent[v] = u;
+                    queue.push_back(v);
+                }
+            }
+        }
+        for (e, &(u, v)) in t.edges.iter().enumerate() {
+            tree_edge[e] = !t.is_dummy(u) && (parent[v] == u || parent[u] == v);
+        }
+        let mut dummy_edge = HashMap::new();
+        for (e, &(d, w)) in t
+            .edges
+            .iter()
+            .enumerate()
+            .filter(|(_, &(d, _))| t.is_dummy(d))
+        {
+            let shallower = |&f: &usize| depth[w] < depth[t.edges[f].1];
+            if dummy_edge.get(&d).is_none_or(shallower) {
+                dummy_edge.insert(d, e);
+            }
+        }
+        for (&d, &e) in &dummy_edge {
+            let w = t.edges[e].1;
+            tree_edge[e] = true;
+            parent[d] = w;
+            depth[d] = depth[w] + 1;
+        }
+
+        // the non-tree edges form a spanning tree of the triangles
+        let mut dual = vec![vec![]; t.num_triangles];
+        for e in (0..t.edges.len()).filter(|&e| !tree_edge[e]) {
+            let [a, b] = t.edge_triangles[e];
+            dual[a].push((b, e));
+            dual[b].push((a, e));
+        }
+        let mut dual_parent_edge = vec![usize::MAX; t.num_triangles];
+        let mut seen = vec![false; t.num_triangles];
+        let mut order = vec![];
+        let mut stack = vec![0];
+        seen[0] = true;
+        while let Some(a) = stack.pop() {
+            order.push(a);
+            for &(b, e) in &dual[a] {
+                if !seen[b] {
+                    seen[b] = true;
+                    dual_parent_edge[b] = e;
+                    stack.push(b);
+                }
+            }
+        }
+        assert_eq!(order.len(), t.num_triangles, "the embedding is not planar");
+        let carried = t.dummy_triangle.values().collect::<HashSet<_>>();
+        // subtree sizes and carried dummies, and a preorder interval per triangle
+        let mut size = vec![1i64; t.num_triangles];
+        let mut dummies = (0..t.num_triangles)
+            .map(|a| carried.contains(&a) as i64)
+            .collect::<Vec<_>>();
+        let mut dual_parent = vec![usize::MAX; t.num_triangles];
+        for &a in &order[1..] {
+            let [x, y] = t.edge_triangles[dual_parent_edge[a]];
+            dual_parent[a] = if x == a { y } else { x };
+        }
+        for &a in order.iter().rev().filter(|&&a| a != 0) {
+            size[dual_parent[a]] += size[a];
+            dummies[dual_parent[a]] += dummies[a];
+        }
+        let mut tin = vec![0; t.num_triangles];
+        let mut counter = 0;
+        let mut children = vec![vec![]; t.num_triangles];
+        for &a in &order[1..] {
+            children[dual_parent[a]].push(a);
+        }
+        let mut stack = vec![0];
+        while let Some(a) = stack.pop() {
+            tin[a] = counter;
+            counter += 1;
+            stack.extend(&children[a]);
+        }
+        let in_subtree =
+            |a: usize, root: usize| tin[a] >= tin[root] && tin[a] < tin[root] + size[root] as usize;
+
+        // LcaUtil roots its tree at node 0
+        let relabel = |u: usize| match u {
+            0 => root,
+            u if u == root => 0,
+            u => u,
+        };
+        let mut tree = Graph::with_node_count(t.num_nodes);
+        for (u, &p) in parent.iter().enumerate() {
+            if p != usize::MAX {
+                tree.add_edge(relabel(u), relabel(p));
+            }
+        }
+        let lca = LcaUtil::new(&tree);
+
+        // (separator size, larger side, candidate)
+        let mut best: Option<(usize, usize, usize)> = None;
+        let mut consider = |size: usize, inside: usize, candidate: usize| {
+            let outside = n - size - inside;
+            let larger = inside.max(outside);
+            let key = |(s, l): (usize, usize)| match l > max_side {
+                true => (1, l, s),
+                false => (0, s, l),
+            };
+            if best.is_none_or(|(s, l, _)| key((size, larger)) < key((s, l))) {
+                best = Some((size, larger, candidate));
+            }
+        };
+
+        // candidates 0..edges are cycles, edges.. are levels
+        let mut inside_of = HashMap::new();
+        for e in (0..t.edges.len()).filter(|&e| !tree_edge[e]) {
+            let (u, v) = t.edges[e];
+            let [a, b] = t.edge_triangles[e];
+            let child = if dual_parent_edge[a] == e { a } else { b };
+            let l = relabel(lca.query(relabel(u), relabel(v)));
+            let length = (depth[u] + depth[v] - 2 * depth[l] + 1) as i64;
+            let interior = (size[child] - length) / 2 + 1;
+            let mut carried_inside = dummies[child];
+            for x in [u, v].into_iter().filter(|&x| t.is_dummy(x)) {
+                carried_inside -= in_subtree(t.dummy_triangle[&x], child) as i64;
+            }
+            let inside = (interior - carried_inside) as usize;
+            let on_cycle = length as usize - [u, v].iter().filter(|&&x| t.is_dummy(x)).count();
+            inside_of.insert(e, inside);
+            consider(on_cycle, inside, e);
+        }
+        let mut above = 0;
+        for (i, &count) in levels.iter().enumerate() {
+            consider(count, above, t.edges.len() + i);
+            above += count;
+        }
+
+        let (size, _, candidate) = best.unwrap();
+        if candidate >= t.edges.len() {
+            let level = candidate - t.edges.len();
+            let above = levels[..level].iter().sum::<usize>();
+            return PlanarSeparator {
+                separator: self.nodes_iter().filter(|&u| depth[u] == level).collect(),
+                inside: above,
+                outside: n - size - above,
+                from_level: true,
+            };
+        }
+
+        let (u, v) = t.edges[candidate];
+        let l = relabel(lca.query(relabel(u), relabel(v)));
+        let mut separator = HashSet::new();
+        for mut x in [u, v] {
+            separator.insert(x);
+            while x != l {
+                x = parent[x];
+                separator.insert(x);
+            }
+        }
+        separator.retain(|&x| !t.is_dummy(x));
+        let inside = inside_of[&candidate];
+        PlanarSeparator {
+            outside: n - separator.len() - inside,
+            separator,
+            inside,
+            from_level: false,
+        }
+    }
+}
+
+impl GeometricGraph {
+    // the drawing has to be planar, e.g. after planarize
+    pub fn fundamental_cycle_separator(&self, imbalance: f64) -> PlanarSeparator {
+        let embedding = Embedding::from_geometric(self);
+        assert_eq!(
+            embedding.genus(),
+            0,
+            "the drawing has crossings, planarize it first"
+        );
+        self.graph
+            .fundamental_cycle_separator(&embedding, imbalance)
+    }
+}
+
+// cycle separator vs KaHIP on planar instances, with 2 sqrt(2n) as the bound from the
+// lipton tarjan theorem. written as "name n cycle kahip bound" to output/cycle_separator
+pub fn compare_with_kahip(n: usize) -> Vec<(String, usize, usize, usize, f64)> {
+    let mut noise = noise::noise(n);
+    planarize(&mut noise);
+    let instances = [
+        ("delaunay", delaunay::delaunay(n)),
+        ("gabriel", gabriel_graph::gabriel_graph(n)),
+        (
+            "relative_neighborhood",
+            relative_neighborhood::relative_neighborhood(n),
+        ),
+        ("noise_planarized", noise),
+    ];
+
+    let mut res = vec![];
+    for (name, g) in instances {
+        let g = g.largest_connected_component();
+        let n = g.graph.get_num_nodes();
+        let cycle = g.fundamental_cycle_separator(0.33).separator.len();
+        let kahip = g.graph.get_separator_size(Mode::Strong);
+        let bound = 2.0 * (2.0 * n as f64).sqrt();
+        println!(
+            "{}\t{}: n {} cycle {} kahip {} bound {:.0}",
+            Local::now(),
+            name,
+            n,
+            cycle,
+            kahip,
+            bound
+        );
+        res.push((name.to_string(), n, cycle, kahip, bound));
+    }
+
+    fs::write(
+        Config::get()
+            .output("cycle_separator")
+            .join(format!("comparison_{n}")),
+        res.iter()
+            .map(|(name, n, c, k, b)| format!("{} {} {} {} {}\n", name, n, c, k, b))
+            .collect::<String>(),
+    )
+    .unwrap();
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::grid;
+
+    fn check(g: &Graph, sep: &PlanarSeparator, imbalance: f64) {
+        let report = g.verify_separator(&sep.separator, imbalance);
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(
+            sep.inside + sep.outside + sep.separator.len(),
+            g.get_num_nodes()
+        );
+        assert!(report.part_sizes[0] <= sep.inside.max(sep.outside));
+    }
+
+    #[test]
+    fn grid_separator() {
+        let g = grid::generate_grid(30);
+        let embedding = g.planar_embedding().unwrap();
+        let sep = g.fundamental_cycle_separator(&embedding, 0.33);
+        check(&g, &sep, 0.33);
+        // a straight line or a cycle through the center
+        assert!(sep.separator.len() <= 2 * 30);
+    }
+
+    #[test]
+    fn tiny_graphs() {
+        // nothing to cut into two parts, the separator just keeps the sides balanced
+        let cases = [
+            (Graph::with_node_count(0), 0),
+            (Graph::with_node_count(1), 0),
+            (Graph::from_edge_list(vec![(0, 1)]), 1),
+        ];
+        for (g, separator) in cases {
+            let sep = g.fundamental_cycle_separator(&g.planar_embedding().unwrap(), 0.33);
+            assert_eq!(sep.separator.len(), separator);
+            assert_eq!(sep.inside + sep.outside + separator, g.get_num_nodes());
+        }
+    }
+
+    #[test]
+    fn delaunay_separator() {
+        let g = delaunay::delaunay(3_000);
+        let sep = g.fundamental_cycle_separator(0.33);
+        check(&g.graph, &sep, 0.33);
+        assert!((sep.separator.len() as f64) < 2.0 * (2.0 * 3_000f64).sqrt());
+    }
+
+    #[test]
+    fn sides_are_exact() {
+        // the predicted sides are unions of the components left by the separator
+        let g = relative_neighborhood::relative_neighborhood(2_000).largest_connected_component();
+        for imbalance in [0.1, 0.33, 0.6] {
+            let sep = g.fundamental_cycle_separator(imbalance);
+            check(&g.graph, &sep, imbalance);
+            let (_, sizes) = g.graph.separator_components(&sep.separator);
+            let mut reachable = vec![false; sep.inside + 1];
+            reachable[0] = true;
+            for s in sizes {
+                for x in (s..=sep.inside).rev() {
+                    reachable[x] |= reachable[x - s];
+                }
+            }
+            assert!(reachable[sep.inside]);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use hashbrown::HashMap;
+use serde_json::Value;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::projection::Crs;
+use crate::graph::Graph;
+use crate::library;
+use crate::mmap::CsrGraph;
+
+// where graphs, external tools and outputs live. read once from the json file in $RNS_CONFIG
+// (or ./datasets.json if present), $RNS_GRAPH_DIR, $RNS_OUTPUT_DIR and $RNS_SCRIPTS_DIR override
+// single entries.
+// relative dataset paths are resolved against graph_dir, e.g.
+// {"graph_dir": "/data/graphs", "datasets": {"berlin": "berlin-connected"}, "tools": {"kahip": "/opt/kahip"}}
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub graph_dir: PathBuf,
+    pub output_dir: PathBuf,
+    // the python helpers of this repository
+    pub scripts_dir: PathBuf,
+    pub datasets: HashMap<String, PathBuf>,
+    // working directories of the external separator tools
+    pub flowcutter: PathBuf,
+    pub inertial_flowcutter: PathBuf,
+    pub kahip: PathBuf,
+    pub boltzmann_planar: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let datasets = [
+            ("karlsruhe", "karlsruhe-connected-bidirectional"),
+            ("germany", "germany-connected-bidirectional"),
+            ("europe", "europe-connected-bidirectional"),
+            ("karlsruhe-osm", "karlsruhe-osm-connected-bidirectional"),
+        ];
+        Config {
+            graph_dir: PathBuf::from("../Graphs"),
+            output_dir: PathBuf::from("./output"),
+            scripts_dir: PathBuf::from("./scripts"),
+            datasets: datasets
+                .iter()
+                .map(|&(name, dir)| (name.to_string(), PathBuf::from(dir)))
+                .collect(),
+            flowcutter: PathBuf::from("../flow-cutter"),
+            inertial_flowcutter: PathBuf::from("../InertialFlowCutter"),
+            kahip: PathBuf::from("./dependencies/KaHIP/deploy"),
+            boltzmann_planar: PathBuf::from("./dependencies/BoltzmannPlanarGraphs/out"),
+        }
+    }
+}
+
+impl Config {
+    pub fn get() -> &'static Config {
+        static CONFIG: OnceLock<Config> = OnceLock::new();
+        CONFIG.get_or_init(|| {
+            let file = env::var_os("RNS_CONFIG")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("./datasets.json"));
+            let mut config = match fs::read_to_string(&file) {
+                Ok(s) => match serde_json::from_str(&s) {
+                    Ok(json) => Config::from_json(&json),
+                    Err(e) => panic!("malformed config {}: {}", file.display(), e),
+                },
+                Err(_) => Config::default(),
+            };
+            if let Some(dir) = env::var_os("RNS_GRAPH_DIR") {
+                config.graph_dir = dir.into();
+            }
+            if let Some(dir) = env::var_os("RNS_OUTPUT_DIR") {
+                config.output_dir = dir.into();
+            }
+            if let Some(dir) = env::var_os("RNS_SCRIPTS_DIR") {
+                config.scripts_dir = dir.into();
+            }
+            config
+        })
+    }
+
+    // entries missing from the json keep their default
+    pub fn from_json(json: &Value) -> Config {
+        let mut config = Config::default();
+        let path = |v: &Value| v.as_str().map(PathBuf::from);
+
+        if let Some(dir) = json.get("graph_dir").and_then(path) {
+            config.graph_dir = dir;
+        }
+        if let Some(dir) = json.get("output_dir").and_then(path) {
+            config.output_dir = dir;
+        }
+        if let Some(dir) = json.get("scripts_dir").and_then(path) {
+            config.scripts_dir = dir;
+        }
+        if let Some(datasets) = json.get("datasets").and_then(Value::as_object) {
+            for (name, dir) in datasets {
+                if let Some(dir) = path(dir) {
+                    config.datasets.insert(name.clone(), dir);
+                }
+            }
+        }
+        if let Some(tools) = json.get("tools") {
+            for (name, dir) in [
+                ("flowcutter", &mut config.flowcutter),
+                ("inertial_flowcutter", &mut config.inertial_flowcutter),
+                ("kahip", &mut config.kahip),
+                ("boltzmann_planar", &mut config.boltzmann_planar),
+            ] {
+                if let Some(p) = tools.get(name).and_then(path) {
+                    *dir = p;
+                }
+            }
+        }
+        config
+    }
+
+    // subdirectory of the output directory, created if it does not exist yet
+    pub fn output(&self, sub: &str) -> PathBuf {
+        let dir = self.output_dir.join(sub);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub fn script(&self, name: &str) -> PathBuf {
+        self.scripts_dir.join(name)
+    }
+
+    pub fn dataset(&self, name: &str) -> io::Result<PathBuf> {
+        let dir = self.datasets.get(name).ok_or_else(|| {
+            let mut known = self.datasets.keys().cloned().collect::<Vec<_>>();
+            known.sort();
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("unknown dataset {name}, known are {}", known.join(", ")),
+            )
+        })?;
+        Ok(self.graph_dir.join(dir))
+    }
+}
+
+pub struct Dataset;
+
+impl Dataset {
+    pub fn path(name: &str) -> io::Result<PathBuf> {
+        Config::get().dataset(name)
+    }
+
+    pub fn load(name: &str) -> io::Result<Graph> {
+        Graph::from_file(&Dataset::path(name)?)
+    }
+
+    // the mapped csr arrays without building the adjacency sets
+    pub fn load_csr(name: &str) -> io::Result<CsrGraph> {
+        CsrGraph::from_file(&Dataset::path(name)?)
+    }
+
+    // real road networks are stored as wgs84, work on them in meters
+    pub fn load_geometric(name: &str) -> io::Result<GeometricGraph> {
+        let mut g = GeometricGraph::from_file(&Dataset::path(name)?)?;
+        if g.crs == Some(Crs::Wgs84) {
+            g.project_local();
+        }
+        Ok(g)
+    }
+
+    // nested dissection order computed by get_ord
+    pub fn load_order(name: &str) -> Vec<usize> {
+        library::read_to_usize_vec(&Config::get().output("ord").join(name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn config_from_json() {
+        let config = Config::from_json(&json!({
+            "graph_dir": "/data",
+            "datasets": {"berlin": "berlin-connected", "germany": "/elsewhere/germany"},
+            "scripts_dir": "/opt/rns/scripts",
+            "tools": {"kahip": "/opt/kahip"}
+        }));
+        assert_eq!(config.kahip, Path::new("/opt/kahip"));
+        assert_eq!(
+            config.script("visualize.py"),
+            Path::new("/opt/rns/scripts/visualize.py")
+        );
+        assert_eq!(config.flowcutter, Config::default().flowcutter);
+        assert_eq!(
+            config.dataset("berlin").unwrap(),
+            Path::new("/data/berlin-connected")
+        );
+        assert_eq!(
+            config.dataset("germany").unwrap(),
+            Path::new("/elsewhere/germany")
+        );
+        assert_eq!(
+            config.dataset("europe").unwrap(),
+            Path::new("/data/europe-connected-bidirectional")
+        );
+        assert!(config.dataset("atlantis").is_err());
+    }
+
+    #[test]
+    fn output_created_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(&json!({"output_dir": dir.path().join("out")}));
+        assert!(config.output("sep").is_dir());
+    }
+}
@@ -0,0 +1,415 @@
+use hashbrown::HashSet;
+use priority_queue::PriorityQueue;
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use std::fs;
+use std::path::Path;
+
+use crate::graph::Graph;
+use crate::separator::Mode;
+
+#[link(name = "kahip")]
+extern "C" {
+    fn kaffpa(
+        n: *const i32,
+        vwgt: *const i32,
+        xadj: *const i32,
+        adjcwgt: *const i32,
+        adjncy: *const i32,
+        nparts: *const i32,
+        imbalance: *const f64,
+        suppress_output: bool,
+        seed: i32,
+        mode: i32,
+        edgecut: *mut i32,
+        part: *mut i32,
+    );
+}
+
+// number of random starts of the graph growing in addition to a pseudo peripheral node
+const GROWING_TRIALS: usize = 4;
+const FM_PASSES: usize = 10;
+// a pass stops after this many moves without a new best cut
+const FM_MAX_NEGATIVE_MOVES: usize = 100;
+
+fn max_block_size(n: usize, imbalance: f64) -> usize {
+    ((1.0 + imbalance) * n.div_ceil(2) as f64) as usize
+}
+
+// bounds on the second and the first block, indexed by side, when the first block should hold
+// fraction * n nodes. fraction 0.5 gives max_block_size for both
+fn block_limits(n: usize, fraction: f64, imbalance: f64) -> [usize; 2] {
+    [1.0 - fraction, fraction].map(|f| ((1.0 + imbalance) * (f * n as f64).ceil()) as usize)
+}
+
+impl Graph {
+    // kaffpa block per node, the mode is passed on as for node_separator. the native modes are
+    // no kaffpa modes, k_way_partition handles them
+    pub fn kaffpa_partition(
+        &self,
+        nparts: i32,
+        imbalance: f64,
+        seed: i32,
+        mode: Mode,
+    ) -> Vec<usize> {
+        let kahip_mode = mode
+            .kahip()
+            .unwrap_or_else(|| panic!("{:?} is not a KaHIP mode, use k_way_partition", mode));
+        let n = self.get_num_nodes() as i32;
+        let (xadj, adjncy) = self.get_adjacency_array();
+        let mut edgecut = 0;
+        let mut part = vec![0i32; self.get_num_nodes()];
+
+        unsafe {
+            kaffpa(
+                &n,
+                std::ptr::null(),
+                xadj.as_ptr(),
+                std::ptr::null(),
+                adjncy.as_ptr(),
+                &nparts,
+                &imbalance,
+                true,
+                seed,
+                kahip_mode,
+                &mut edgecut,
+                part.as_mut_ptr(),
+            );
+        }
+
+        part.iter().map(|&p| p as usize).collect()
+    }
+
+    // side[u] == true for the nodes of the first block, which should hold fraction * n of them.
+    // kaffpa only bisects evenly
+    pub fn edge_bisection(&self, fraction: f64, imbalance: f64, mode: Mode) -> Vec<bool> {
+        match mode {
+            Mode::Spectral => {
+                let mut side = self.spectral_bisection(fraction, imbalance);
+                self.fm_refinement(&mut side, fraction, imbalance);
+                side
+            }
+            Mode::Native => self.native_edge_bisection(fraction, imbalance),
+            _ if fraction != 0.5 => {
+                panic!("{:?} only bisects evenly, got fraction {}", mode, fraction)
+            }
+            _ => self
+                .kaffpa_partition(2, imbalance, rand::random(), mode)
+                .iter()
+                .map(|&p| p == 0)
+                .collect(),
+        }
+    }
+
+    pub fn cut_edges(&self, side: &[bool]) -> Vec<(usize, usize)> {
+        self.get_edges()
+            .into_iter()
+            .filter(|&(u, v)| u < v && side[u] != side[v])
+            .collect()
+    }
+
+    pub fn cut_size(&self, side: &[bool]) -> usize {
+        self.nodes_iter()
+            .filter(|&u| side[u])
+            .map(|u| self.get_neighbors(u).iter().filter(|&&v| !side[v]).count())
+            .sum()
+    }
+
+    // bfs growing from several start nodes, each followed by FM, the best cut wins
+    pub fn native_edge_bisection(&self, fraction: f64, imbalance: f64) -> Vec<bool> {
+        let n = self.get_num_nodes();
+        if n < 2 {
+            return vec![true; n];
+        }
+        let peripheral = self.pseudo_peripheral_node(0);
+        let starts = std::iter::once(peripheral)
+            .chain((0..GROWING_TRIALS).map(|_| thread_rng().gen_range(0..n)))
+            .collect::<Vec<_>>();
+
+        starts
+            .par_iter()
+            .map(|&start| {
+                let mut side = self.grow_block(start, (fraction * n as f64) as usize);
+                self.fm_refinement(&mut side, fraction, imbalance);
+                side
+            })
+            .min_by_key(|side| self.cut_size(side))
+            .unwrap()
+    }
+
+    fn pseudo_peripheral_node(&self, start: usize) -> usize {
+        let mut u = start;
+        let mut eccentricity = 0;
+        loop {
+            let distances = self.bfs(u);
+            let (v, &d) = distances
+                .iter()
+                .enumerate()
+                .filter(|(_, &d)| d != usize::MAX)
+                .max_by_key(|(_, &d)| d)
+                .unwrap();
+            if d <= eccentricity {
+                return u;
+            }
+            eccentricity = d;
+            u = v;
+        }
+    }
+
+    // the first `size` nodes found by a bfs from start, other components are filled in after it
+    fn grow_block(&self, start: usize, size: usize) -> Vec<bool> {
+        let mut side = vec![false; self.get_num_nodes()];
+        let mut count = 0;
+        for s in std::iter::once(start).chain(self.nodes_iter()) {
+            if side[s] {
+                continue;
+            }
+            side[s] = true;
+            count += 1;
+            let mut queue = std::collections::VecDeque::from([s]);
+            while let Some(u) = queue.pop_front() {
+                if count >= size {
+                    return side;
+                }
+                for &v in self.get_neighbors(u) {
+                    if !side[v] && count < size {
+                        side[v] = true;
+                        count += 1;
+                        queue.push_back(v);
+                    }
+                }
+            }
+        }
+        side
+    }
+
+    fn fm_gain(&self, side: &[bool], u: usize) -> i64 {
+        self.get_neighbors(u)
+            .iter()
+            .map(|&v| if side[v] != side[u] { 1 } else { -1 })
+            .sum()
+    }
+
+    // Fiduccia-Mattheyses passes moving single nodes between the blocks, every pass is rolled
+    // back to its best prefix. moves never violate the balance of block_limits
+    pub fn fm_refinement(&self, side: &mut [bool], fraction: f64, imbalance: f64) {
+        let n = self.get_num_nodes();
+        let max_block = block_limits(n, fraction, imbalance);
+        let mut sizes = [0, 0];
+        for &s in side.iter() {
+            sizes[s as usize] += 1;
+        }
+
+        for _ in 0..FM_PASSES {
+            // one queue per block, holding the nodes that could leave it
+            let mut queues = [PriorityQueue::new(), PriorityQueue::new()];
+            for u in 0..n {
+                if self.get_neighbors(u).iter().any(|&v| side[v] != side[u]) {
+                    queues[side[u] as usize].push(u, self.fm_gain(side, u));
+                }
+            }
+
+            let mut locked = HashSet::new();
+            let mut moves = vec![];
+            let (mut cut_delta, mut best_delta, mut best_prefix) = (0i64, 0i64, 0);
+
+            loop {
+                let candidates = [0, 1].map(|b| queues[b].peek().map(|(&u, &g)| (u, g)));
+                let choice = [0, 1]
+                    .into_iter()
+                    .filter(|&b| sizes[1 - b] < max_block[1 - b])
+                    .filter_map(|b| candidates[b].map(|(u, g)| (b, u, g)))
+                    // prefer the larger block when gains tie
+                    .max_by_key(|&(b, _, g)| (g, sizes[b]));
+                let Some((from, u, gain)) = choice else {
+                    break;
+                };
+                queues[from].pop();
+
+                side[u] = !side[u];
+                sizes[from] -= 1;
+                sizes[1 - from] += 1;
+                locked.insert(u);
+                moves.push(u);
+                cut_delta -= gain;
+
+                let overloaded = sizes[0] > max_block[0] || sizes[1] > max_block[1];
+                if cut_delta < best_delta && !overloaded {
+                    best_delta = cut_delta;
+                    best_prefix = moves.len();
+                }
+                if moves.len() - best_prefix > FM_MAX_NEGATIVE_MOVES {
+                    break;
+                }
+
+                for &v in self.get_neighbors(u) {
+                    if locked.contains(&v) {
+                        continue;
+                    }
+                    let gain = self.fm_gain(side, v);
+                    let block = side[v] as usize;
+                    if queues[block].change_priority(&v, gain).is_none() {
+                        queues[block].push(v, gain);
+                    }
+                }
+            }
+
+            for &u in moves[best_prefix..].iter().rev() {
+                side[u] = !side[u];
+                sizes[!side[u] as usize] -= 1;
+                sizes[side[u] as usize] += 1;
+            }
+            if best_prefix == 0 {
+                break;
+            }
+        }
+    }
+
+    // each separator node joins the side most of its already placed neighbors are on, as long
+    // as that side has room, the components are split as in refine_separator
+    pub fn separator_to_edge_cut(&self, separator: &HashSet<usize>, imbalance: f64) -> Vec<bool> {
+        let max_block = max_block_size(self.get_num_nodes(), imbalance);
+        let placed = self.separator_sides(separator);
+        let mut side = placed
+            .iter()
+            .map(|s| s.unwrap_or(false))
+            .collect::<Vec<_>>();
+        let mut sizes = [0, 0];
+        for s in placed.iter().flatten() {
+            sizes[*s as usize] += 1;
+        }
+
+        let mut separator = separator.iter().copied().collect::<Vec<_>>();
+        separator.sort_unstable();
+        let mut done = placed.iter().map(|s| s.is_some()).collect::<Vec<_>>();
+        for s in separator {
+            let votes = self
+                .get_neighbors(s)
+                .iter()
+                .filter(|&&v| done[v])
+                .map(|&v| if side[v] { 1 } else { -1 })
+                .sum::<i64>();
+            let mut preferred = if votes == 0 {
+                sizes[1] < sizes[0]
+            } else {
+                votes > 0
+            };
+            if sizes[preferred as usize] >= max_block {
+                preferred = !preferred;
+            }
+            side[s] = preferred;
+            sizes[preferred as usize] += 1;
+            done[s] = true;
+        }
+        side
+    }
+
+    // (n, cut) for every bisection of a recursive edge bisection down to 100 nodes, written
+    // like traverse_separator_tree writes (n, s)
+    pub fn recursive_edge_cut(
+        &self,
+        mode: Mode,
+        imbalance: f64,
+        out_file: &Path,
+    ) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        let mut layer = vec![self.clone()];
+        while !layer.is_empty() {
+            let next = layer
+                .into_par_iter()
+                .map(|g| {
+                    let side = g.edge_bisection(0.5, imbalance, mode);
+                    let blocks = [true, false].map(|b| {
+                        let nodes = g.nodes_iter().filter(|&u| side[u] == b).collect::<Vec<_>>();
+                        g.induced_subgraph(&nodes)
+                    });
+                    ((g.get_num_nodes(), g.cut_size(&side)), blocks)
+                })
+                .collect::<Vec<_>>();
+
+            layer = vec![];
+            for (stat, blocks) in next {
+                res.push(stat);
+                layer.extend(blocks.into_iter().filter(|b| b.get_num_nodes() > 100));
+            }
+        }
+
+        fs::write(
+            out_file,
+            res.iter()
+                .map(|(n, c)| format!("{} {}\n", n, c))
+                .collect::<String>(),
+        )
+        .unwrap();
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::grid;
+
+    #[test]
+    fn grid_bisection() {
+        let g = grid::generate_grid(10);
+        let side = g.native_edge_bisection(0.5, 0.03);
+        assert_eq!(g.cut_size(&side), 10);
+        assert_eq!(g.cut_edges(&side).len(), 10);
+        let first = side.iter().filter(|&&s| s).count();
+        assert!(first.max(100 - first) <= max_block_size(100, 0.03));
+
+        // the vertex cover of a straight cut is one of its sides
+        assert_eq!(g.vertex_cover_of_cut(&side).len(), 10);
+    }
+
+    #[test]
+    fn uneven_bisection() {
+        let g = grid::generate_grid(12);
+        for mode in [Mode::Native, Mode::Spectral] {
+            let side = g.edge_bisection(1.0 / 3.0, 0.03, mode);
+            let first = side.iter().filter(|&&s| s).count();
+            // 48 and 96 nodes, each up to 3% more, rounded up
+            assert!(first <= 50 && 144 - first <= 99, "{:?}: {}", mode, first);
+        }
+    }
+
+    #[test]
+    fn fm_repairs_a_bad_start() {
+        let g = grid::generate_grid(10);
+        // every other column on the first side
+        let mut side = (0..100).map(|u| (u % 10) % 2 == 0).collect::<Vec<_>>();
+        let before = g.cut_size(&side);
+        g.fm_refinement(&mut side, 0.5, 0.1);
+        assert!(g.cut_size(&side) < before);
+        let first = side.iter().filter(|&&s| s).count();
+        assert!(first.max(100 - first) <= max_block_size(100, 0.1));
+    }
+
+    #[test]
+    fn separator_to_cut() {
+        let g = grid::generate_grid(10);
+        let column = (0..10).map(|i| i * 10 + 5).collect::<HashSet<_>>();
+        let side = g.separator_to_edge_cut(&column, 0.03);
+        assert_eq!(g.cut_size(&side), 10);
+    }
+
+    #[test]
+    fn recursive_stats() {
+        let g = grid::generate_grid(20);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let stats = g.recursive_edge_cut(Mode::Native, 0.03, file.path());
+        assert_eq!(stats[0].0, 400);
+        assert!(stats[0].1 <= 24);
+        assert!(stats.iter().skip(1).all(|&(n, _)| n < 400));
+        let written = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(written.lines().count(), stats.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "not a KaHIP mode")]
+    fn kaffpa_rejects_native_modes() {
+        grid::generate_grid(4).kaffpa_partition(2, 0.03, 0, Mode::Native);
+    }
+}
@@ -0,0 +1,350 @@
+use geo::Point;
+use ordered_float::OrderedFloat;
+use serde_json::json;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::container::Attributes;
+use crate::dataset::Dataset;
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::projection::Crs;
+use crate::graph::{noise, Graph};
+
+// small cut-outs of the real road networks, extracted into ./fixtures by create_all once the
+// datasets are available. (name, dataset, center longitude, center latitude, number of nodes)
+pub const FIXTURES: [(&str, &str, f64, f64, usize); 3] = [
+    ("karlsruhe-center", "karlsruhe", 8.4037, 49.0094, 3_000),
+    ("karlsruhe-blackforest", "karlsruhe", 8.4400, 48.8000, 3_000),
+    ("germany-ruhr", "germany", 7.0000, 51.4500, 5_000),
+];
+
+// generated with the noise model, committed in ./fixtures until the cut-outs above are.
+// (name, number of nodes)
+pub const GENERATED: [(&str, usize); 2] = [("noise-1k", 1_000), ("noise-2k", 2_000)];
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(format!("{name}.graph"))
+}
+
+// the fixture, real cut-outs projected to meters. fails if the file has not been created yet
+pub fn load(name: &str) -> io::Result<GeometricGraph> {
+    let file = fixture_path(name);
+    if !file.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("fixture {name} missing, see fixtures/README.md"),
+        ));
+    }
+    let mut g = GeometricGraph::from_file(&file)?;
+    if g.crs == Some(Crs::Wgs84) {
+        g.project_local();
+    }
+    Ok(g)
+}
+
+// the `nodes` nodes closest to center by network distance, as a connected graph
+pub fn extract(g: &GeometricGraph, center: Point, crs: Crs, nodes: usize) -> GeometricGraph {
+    let n = g.graph.get_num_nodes();
+    let start = (0..n)
+        .min_by_key(|&u| OrderedFloat(crs.distance(g.get_position(u), center)))
+        .unwrap();
+
+    let mut distances = vec![f64::INFINITY; n];
+    let mut new_index = vec![usize::MAX; n];
+    let mut settled = Vec::new();
+    let mut heap = BinaryHeap::from([Reverse((OrderedFloat(0.0), start))]);
+    distances[start] = 0.0;
+
+    while let Some(Reverse((OrderedFloat(d), u))) = heap.pop() {
+        if new_index[u] != usize::MAX || d > distances[u] {
+            continue;
+        }
+        new_index[u] = settled.len();
+        settled.push(u);
+        if settled.len() == nodes {
+            break;
+        }
+        for &v in g.graph.get_neighbors(u) {
+            let dv = d + crs.distance(g.get_position(u), g.get_position(v));
+            if dv < distances[v] {
+                distances[v] = dv;
+                heap.push(Reverse((OrderedFloat(dv), v)));
+            }
+        }
+    }
+
+    let data = settled
+        .iter()
+        .map(|&u| {
+            g.graph
+                .get_neighbors(u)
+                .iter()
+                .filter(|&&v| new_index[v] != usize::MAX)
+                .map(|&v| new_index[v])
+                .collect()
+        })
+        .collect();
+    let positions = settled.iter().map(|&u| g.get_position(u)).collect();
+    GeometricGraph::new(Graph::new(data), positions)
+}
+
+// needs the datasets from the catalog, the extracted files are meant to be committed
+pub fn create_all() -> io::Result<()> {
+    for (name, dataset, lon, lat, nodes) in FIXTURES {
+        let g = GeometricGraph::from_file(&Dataset::path(dataset)?)?;
+        let fixture = extract(&g, Point::new(lon, lat), Crs::Wgs84, nodes);
+        let attributes = Attributes {
+            crs: Some(Crs::Wgs84),
+            metadata: json!({
+                "source": dataset,
+                "center": [lon, lat],
+                "nodes": nodes,
+                "extraction": "network distance ball, fixtures::extract",
+                "license": "OpenStreetMap contributors, ODbL",
+            }),
+            ..Default::default()
+        };
+        fixture.save_with_attributes(&fixture_path(name), &attributes)?;
+        println!(
+            "{name}: {} nodes, {} edges",
+            fixture.graph.get_num_nodes(),
+            fixture.graph.get_num_edges()
+        );
+    }
+    Ok(())
+}
+
+// the generator is not seeded, rerunning it changes the files and the expected test values
+pub fn create_generated() -> io::Result<()> {
+    for (name, nodes) in GENERATED {
+        let g = noise::noise(nodes);
+        let attributes = Attributes {
+            metadata: json!({
+                "source": "noise::noise",
+                "nodes": nodes,
+                "extraction": "generated, no real road data",
+            }),
+            ..Default::default()
+        };
+        g.save_with_attributes(&fixture_path(name), &attributes)?;
+        println!(
+            "{name}: {} nodes, {} edges",
+            g.graph.get_num_nodes(),
+            g.graph.get_num_edges()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cch;
+    use crate::graph::planar::planarize;
+    use crate::graph::{delaunay, example};
+    use crate::mmap::CsrGraph;
+
+    // regression values of the committed fixtures
+    struct Expected {
+        name: &'static str,
+        nodes: usize,
+        edges: usize,
+        contracted: usize,
+        diameter: usize,
+        spectral_separator: usize,
+        // of geometric_order with the default policy
+        top_level_separator: usize,
+        tree_entries: usize,
+        separator_sum: usize,
+    }
+
+    const EXPECTED: [Expected; 2] = [
+        Expected {
+            name: "noise-1k",
+            nodes: 1_000,
+            edges: 1_259,
+            contracted: 544,
+            diameter: 56,
+            spectral_separator: 12,
+            top_level_separator: 19,
+            tree_entries: 37,
+            separator_sum: 181,
+        },
+        Expected {
+            name: "noise-2k",
+            nodes: 2_000,
+            edges: 2_475,
+            contracted: 1_027,
+            diameter: 83,
+            spectral_separator: 13,
+            top_level_separator: 28,
+            tree_entries: 76,
+            separator_sum: 347,
+        },
+    ];
+
+    fn all_fixtures() -> Vec<(&'static Expected, GeometricGraph)> {
+        EXPECTED
+            .iter()
+            .map(|e| (e, load(e.name).unwrap()))
+            .collect()
+    }
+
+    // nested dissection by median splits along the longer side of the bounding box, the nodes
+    // of the first half with a neighbor in the second half are the separator
+    fn geometric_order(g: &GeometricGraph, mut nodes: Vec<usize>, order: &mut Vec<usize>) {
+        if nodes.len() <= 2 {
+            order.extend(nodes);
+            return;
+        }
+        let extent = |f: fn(Point) -> f64| {
+            let values = nodes.iter().map(|&u| f(g.get_position(u)));
+            values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+        };
+        let key: fn(Point) -> f64 = if extent(Point::x) >= extent(Point::y) {
+            Point::x
+        } else {
+            Point::y
+        };
+        nodes.sort_by_key(|&u| (OrderedFloat(key(g.get_position(u))), u));
+        let second = nodes.split_off(nodes.len() / 2);
+        let in_second = second.iter().copied().collect::<hashbrown::HashSet<_>>();
+        let (separator, first) = nodes.into_iter().partition::<Vec<_>, _>(|&u| {
+            g.graph
+                .get_neighbors(u)
+                .iter()
+                .any(|v| in_second.contains(v))
+        });
+        geometric_order(g, first, order);
+        geometric_order(g, second, order);
+        order.extend(separator);
+    }
+
+    // the in-memory and the streamed separator sizes agree and every separator of the order
+    // splits its cell. returns the sizes and the top-level separator
+    fn check_cch(g: &GeometricGraph) -> (Vec<(usize, usize)>, Vec<usize>) {
+        let n = g.graph.get_num_nodes();
+        let mut order = vec![];
+        geometric_order(g, g.graph.nodes_iter().collect(), &mut order);
+        assert_eq!(order.len(), n);
+
+        let dir = tempfile::tempdir().unwrap();
+        g.graph.save_routingkit(dir.path()).unwrap();
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let policy = cch::SeparatorTreePolicy::default();
+        let mut sizes =
+            cch::compute_separator_sizes_from_order(&g.graph, &order, &policy, out.path());
+        let mut streamed =
+            cch::compute_separator_sizes_from_dir(dir.path(), &order, &policy, out.path()).unwrap();
+        sizes.sort_unstable();
+        streamed.sort_unstable();
+        assert_eq!(sizes, streamed);
+        assert!(!sizes.is_empty());
+
+        let reports = cch::verify_order_separators(&g.graph, &order, &policy, 0.5);
+        assert!(reports.iter().all(|r| r.is_separator));
+
+        let top = cch::get_top_level_separator(&g.graph, &order, &policy);
+        assert!((top.len() as f64) < 2.0 * (n as f64).sqrt());
+        (sizes, top)
+    }
+
+    #[test]
+    fn cch_on_delaunay() {
+        check_cch(&delaunay::delaunay(2_000));
+    }
+
+    #[test]
+    fn extract_ball() {
+        let g = delaunay::delaunay(2_000);
+        let center = Point::new(0.5, 0.5);
+        let ball = extract(&g, center, Crs::Planar, 300);
+        assert_eq!(ball.graph.get_num_nodes(), 300);
+        assert!(ball.graph.is_connected());
+        assert!(ball.graph.is_undirected());
+
+        // the first node is the one closest to the center
+        let closest = g
+            .positions
+            .iter()
+            .map(|&p| Crs::Planar.distance(p, center))
+            .fold(f64::INFINITY, f64::min);
+        assert_eq!(Crs::Planar.distance(ball.get_position(0), center), closest);
+
+        let small = extract(&example::example_c4(), center, Crs::Planar, 10);
+        assert_eq!(small.graph.get_num_nodes(), 4);
+    }
+
+    #[test]
+    fn fixtures_are_connected() {
+        for (e, g) in all_fixtures() {
+            assert_eq!(g.graph.get_num_nodes(), e.nodes, "{}", e.name);
+            assert_eq!(g.graph.get_num_edges(), e.edges, "{}", e.name);
+            assert!(g.graph.is_connected());
+            assert!(g.graph.is_undirected());
+        }
+    }
+
+    #[test]
+    fn fixture_contraction() {
+        for (e, mut g) in all_fixtures() {
+            g.contract_and_llc();
+            assert_eq!(g.graph.get_num_nodes(), e.contracted, "{}", e.name);
+            assert!(g.graph.is_connected());
+        }
+    }
+
+    #[test]
+    fn fixture_planarize() {
+        // the noise model draws its graphs without crossings
+        for (e, g) in all_fixtures() {
+            let mut planar = g.clone();
+            planarize(&mut planar);
+            assert_eq!(planar.graph.get_num_nodes(), e.nodes, "{}", e.name);
+            assert_eq!(planar.graph.get_num_edges(), e.edges, "{}", e.name);
+        }
+    }
+
+    #[test]
+    fn fixture_diameter() {
+        for (e, g) in all_fixtures() {
+            assert_eq!(
+                g.graph.get_hop_diameter_primitive(),
+                e.diameter,
+                "{}",
+                e.name
+            );
+            assert_eq!(g.graph.diameter_ifub(), Some(e.diameter), "{}", e.name);
+        }
+    }
+
+    #[test]
+    fn fixture_separator() {
+        for (e, g) in all_fixtures() {
+            let n = g.graph.get_num_nodes();
+            let sep = g.graph.spectral_separator(0.33);
+            assert_eq!(sep.len(), e.spectral_separator, "{}", e.name);
+            let parts = g.graph.get_subgraphs_map(&sep);
+            assert!(parts.len() >= 2);
+            assert!(parts
+                .iter()
+                .all(|p| p.len() as f64 <= 1.33 * n as f64 / 2.0 + 1.0));
+        }
+    }
+
+    #[test]
+    fn fixture_cch() {
+        for (e, g) in all_fixtures() {
+            let (sizes, top) = check_cch(&g);
+            assert_eq!(top.len(), e.top_level_separator, "{}", e.name);
+            assert_eq!(sizes.len(), e.tree_entries, "{}", e.name);
+            let sum = sizes.iter().map(|&(_, s)| s).sum::<usize>();
+            assert_eq!(sum, e.separator_sum, "{}", e.name);
+        }
+    }
+}
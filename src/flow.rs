@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+pub const INFINITE: usize = usize::MAX / 2;
+
+struct Arc {
+    head: usize,
+    capacity: usize,
+    // index of the reverse arc in the adjacency of head
+    reverse: usize,
+}
+
+// residual network for small integral max-flow problems, augments along shortest paths
+pub struct FlowNetwork {
+    arcs: Vec<Vec<Arc>>,
+}
+
+impl FlowNetwork {
+    pub fn new(n: usize) -> Self {
+        FlowNetwork {
+            arcs: (0..n).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    pub fn add_node(&mut self) -> usize {
+        self.arcs.push(Vec::new());
+        self.arcs.len() - 1
+    }
+
+    pub fn add_arc(&mut self, u: usize, v: usize, capacity: usize) {
+        let (ru, rv) = (self.arcs[v].len(), self.arcs[u].len());
+        self.arcs[u].push(Arc {
+            head: v,
+            capacity,
+            reverse: ru,
+        });
+        self.arcs[v].push(Arc {
+            head: u,
+            capacity: 0,
+            reverse: rv,
+        });
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize, capacity: usize) {
+        self.add_arc(u, v, capacity);
+        self.add_arc(v, u, capacity);
+    }
+
+    // stops early once the flow reaches limit
+    pub fn max_flow(&mut self, s: usize, t: usize, limit: usize) -> usize {
+        let mut flow = 0;
+        while flow < limit {
+            let mut parent = vec![None; self.arcs.len()];
+            let mut queue = VecDeque::from([s]);
+            while let Some(u) = queue.pop_front() {
+                if u == t {
+                    break;
+                }
+                for (i, arc) in self.arcs[u].iter().enumerate() {
+                    if arc.capacity > 0 && arc.head != s && parent[arc.head].is_none() {
+                        parent[arc.head] = Some((u, i));
+                        queue.push_back(arc.head);
+                    }
+                }
+            }
+            if parent[t].is_none() {
+                break;
+            }
+
+            let mut bottleneck = limit - flow;
+            let mut v = t;
+            while let Some((u, i)) = parent[v] {
+                bottleneck = bottleneck.min(self.arcs[u][i].capacity);
+                v = u;
+            }
+            let mut v = t;
+            while let Some((u, i)) = parent[v] {
+                self.arcs[u][i].capacity -= bottleneck;
+                let reverse = self.arcs[u][i].reverse;
+                self.arcs[v][reverse].capacity += bottleneck;
+                v = u;
+            }
+            flow += bottleneck;
+        }
+        flow
+    }
+
+    // nodes reachable from s in the residual network, the source side of the minimum cut
+    // closest to s
+    pub fn source_side(&self, s: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.arcs.len()];
+        seen[s] = true;
+        let mut stack = vec![s];
+        while let Some(u) = stack.pop() {
+            for arc in &self.arcs[u] {
+                if arc.capacity > 0 && !seen[arc.head] {
+                    seen[arc.head] = true;
+                    stack.push(arc.head);
+                }
+            }
+        }
+        seen
+    }
+
+    // nodes that can still reach t in the residual network, the sink side of the minimum cut
+    // closest to t
+    pub fn sink_side(&self, t: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.arcs.len()];
+        seen[t] = true;
+        let mut stack = vec![t];
+        while let Some(v) = stack.pop() {
+            for arc in &self.arcs[v] {
+                // the reverse arc arc.head -> v has residual capacity
+                let residual = self.arcs[arc.head][arc.reverse].capacity;
+                if residual > 0 && !seen[arc.head] {
+                    seen[arc.head] = true;
+                    stack.push(arc.head);
+                }
+            }
+        }
+        seen
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn two_paths() {
+        // s -> a -> t and s -> b -> t with a bottleneck of 1 on the second path
+        let mut f = FlowNetwork::new(4);
+        let (s, a, b, t) = (0, 1, 2, 3);
+        f.add_arc(s, a, 2);
+        f.add_arc(a, t, 2);
+        f.add_arc(s, b, 5);
+        f.add_arc(b, t, 1);
+        f.add_arc(a, b, 1);
+        assert_eq!(f.max_flow(s, t, INFINITE), 3);
+        assert_eq!(f.source_side(s), vec![true, false, true, false]);
+        assert_eq!(f.sink_side(t), vec![false, false, false, true]);
+    }
+}
@@ -145,9 +145,8 @@ pub fn build_cbrt_bridged(n: usize, width: f64, height: f64) -> GeometricGraph {
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-
     use super::*;
+    use crate::dataset::Config;
 
     #[test]
     fn blobs() {
@@ -163,6 +162,6 @@ mod tests {
     fn test_bridged() {
         let g = build_cbrt_bridged(50, 200.0, 200.0);
         g.graph.info();
-        g.save(Path::new("output/cbrt_bridged"));
+        g.save_routingkit(&Config::get().output("graphs").join("cbrt_bridged"));
     }
 }
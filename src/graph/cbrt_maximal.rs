@@ -57,11 +57,10 @@ pub fn generate_cbrt_maximal(n: usize) -> Graph {
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-
     use crate::graph::example;
 
     use super::*;
+    use crate::dataset::Config;
 
     #[test]
     fn cbrt_maximal() {
@@ -83,6 +82,10 @@ mod tests {
         let mut g = generate_cbrt_maximal(10000);
         g.approx_degrees(&example::DEGREE_DISTRIBUTION_GER);
         g.info();
-        g.save(Path::new("./output/cbrt_maximal_avg_deg_20k/"));
+        g.save_routingkit(
+            &Config::get()
+                .output("graphs")
+                .join("cbrt_maximal_avg_deg_20k"),
+        );
     }
 }
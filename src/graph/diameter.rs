@@ -0,0 +1,381 @@
+use chrono::Local;
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use super::geometric_graph::GeometricGraph;
+use super::{delaunay, example, gabriel_graph, noise, relative_neighborhood, Graph};
+use crate::dataset::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiameterBounds<T> {
+    pub lower: T,
+    pub upper: T,
+    // lower == upper, false if the time budget ran out first
+    pub exact: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eccentricities<T> {
+    pub eccentricity: Vec<T>,
+    pub radius: T,
+    pub diameter: T,
+    // nodes with eccentricity == radius
+    pub center: Vec<usize>,
+    // nodes with eccentricity == diameter
+    pub periphery: Vec<usize>,
+    // shortest path trees computed, n for the naive approach
+    pub searches: usize,
+}
+
+fn eccentricity(distances: &[f64]) -> f64 {
+    distances.iter().copied().fold(0.0, f64::max)
+}
+
+fn farthest(distances: &[f64]) -> usize {
+    (0..distances.len())
+        .max_by_key(|&u| OrderedFloat(distances[u]))
+        .unwrap()
+}
+
+// node with the smallest larger distance to both ends, about the middle of their shortest path
+fn middle(from_a: &[f64], from_b: &[f64]) -> usize {
+    (0..from_a.len())
+        .min_by_key(|&u| OrderedFloat(from_a[u].max(from_b[u])))
+        .unwrap()
+}
+
+// iFUB (Crescenzi et al. 2013): eccentricities of the nodes farthest from a central node u
+// give the lower bound, once all nodes with d(u, x) > r are done no pair among the rest can be
+// further apart than 2r. u is the middle of two double sweeps (4-sweep)
+fn ifub(
+    n: usize,
+    distances: &(dyn Fn(usize) -> Vec<f64> + Sync),
+    budget: Option<Duration>,
+) -> Option<DiameterBounds<f64>> {
+    if n <= 1 {
+        return Some(DiameterBounds {
+            lower: 0.0,
+            upper: 0.0,
+            exact: true,
+        });
+    }
+    let start = Instant::now();
+
+    let from_0 = distances(0);
+    if from_0.iter().any(|d| d.is_infinite()) {
+        return None;
+    }
+    let mut lower = eccentricity(&from_0);
+    let mut u = 0;
+    let mut from_u = from_0;
+    for _ in 0..2 {
+        let a = farthest(&from_u);
+        let from_a = distances(a);
+        let b = farthest(&from_a);
+        let from_b = distances(b);
+        lower = lower.max(eccentricity(&from_a)).max(eccentricity(&from_b));
+        u = middle(&from_a, &from_b);
+        from_u = distances(u);
+    }
+    lower = lower.max(eccentricity(&from_u));
+    let mut upper = 2.0 * eccentricity(&from_u);
+
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&x| std::cmp::Reverse(OrderedFloat(from_u[x])));
+    let batch = 4 * rayon::current_num_threads();
+    let mut i = 0;
+    while lower < upper {
+        if budget.is_some_and(|b| start.elapsed() > b) {
+            break;
+        }
+        let end = (i + batch).min(n);
+        let eccentricities = order[i..end]
+            .par_iter()
+            .map(|&x| eccentricity(&distances(x)))
+            .collect::<Vec<_>>();
+        lower = eccentricities.into_iter().fold(lower, f64::max);
+        upper = match order.get(end) {
+            Some(&next) => upper.min(lower.max(2.0 * from_u[next])),
+            None => lower,
+        };
+        i = end;
+    }
+
+    Some(DiameterBounds {
+        lower,
+        upper,
+        exact: lower >= upper,
+    })
+}
+
+// BoundingDiameters (Takes and Kosters 2013): every search from v bounds all other eccentricities
+// by max(d(v, w), ecc(v) - d(v, w)) <= ecc(w) <= ecc(v) + d(v, w). the next search alternates
+// between the node with the smallest lower and the one with the largest upper bound
+fn bounding_eccentricities(
+    n: usize,
+    distances: &dyn Fn(usize) -> Vec<f64>,
+) -> Option<(Vec<f64>, usize)> {
+    let mut lower = vec![0.0; n];
+    let mut upper = vec![f64::INFINITY; n];
+    let mut candidates = (0..n).collect::<Vec<_>>();
+    let mut searches = 0;
+    let mut pick_low = true;
+    while !candidates.is_empty() {
+        let v = if pick_low {
+            *candidates
+                .iter()
+                .min_by_key(|&&w| OrderedFloat(lower[w]))
+                .unwrap()
+        } else {
+            *candidates
+                .iter()
+                .max_by_key(|&&w| OrderedFloat(upper[w]))
+                .unwrap()
+        };
+        pick_low = !pick_low;
+
+        let from_v = distances(v);
+        searches += 1;
+        if from_v.iter().any(|d| d.is_infinite()) {
+            return None;
+        }
+        let ecc = eccentricity(&from_v);
+        lower[v] = ecc;
+        upper[v] = ecc;
+        candidates.retain(|&w| {
+            let d = from_v[w];
+            lower[w] = lower[w].max(d).max(ecc - d);
+            upper[w] = upper[w].min(ecc + d);
+            lower[w] < upper[w]
+        });
+    }
+    // lower and upper only differ by rounding for finished nodes
+    Some((lower, searches))
+}
+
+fn summarize<T: Copy + PartialOrd>(eccentricity: Vec<T>, searches: usize) -> Eccentricities<T> {
+    let extreme = |better: fn(&T, &T) -> bool| {
+        eccentricity
+            .iter()
+            .copied()
+            .reduce(|a, b| if better(&b, &a) { b } else { a })
+            .unwrap()
+    };
+    let radius = extreme(|a, b| a < b);
+    let diameter = extreme(|a, b| a > b);
+    let nodes_with = |value: T| {
+        (0..eccentricity.len())
+            .filter(|&u| eccentricity[u] == value)
+            .collect()
+    };
+    Eccentricities {
+        center: nodes_with(radius),
+        periphery: nodes_with(diameter),
+        eccentricity,
+        radius,
+        diameter,
+        searches,
+    }
+}
+
+impl Graph {
+    // certified hop diameter bounds, None if the graph is disconnected. without a budget the
+    // bounds are tight, on road networks after a few hundred bfs
+    pub fn diameter_bounds(&self, budget: Option<Duration>) -> Option<DiameterBounds<usize>> {
+        let distances = |s: usize| {
+            self.bfs(s)
+                .into_iter()
+                .map(|d| {
+                    if d == usize::MAX {
+                        f64::INFINITY
+                    } else {
+                        d as f64
+                    }
+                })
+                .collect()
+        };
+        ifub(self.get_num_nodes(), &distances, budget).map(|b| DiameterBounds {
+            lower: b.lower as usize,
+            upper: b.upper as usize,
+            exact: b.exact,
+        })
+    }
+
+    pub fn diameter_ifub(&self) -> Option<usize> {
+        self.diameter_bounds(None).map(|b| b.lower)
+    }
+
+    // exact hop eccentricity of every node, None for disconnected or empty graphs
+    pub fn eccentricities(&self) -> Option<Eccentricities<usize>> {
+        if self.get_num_nodes() == 0 {
+            return None;
+        }
+        let distances = |s: usize| {
+            self.bfs(s)
+                .into_iter()
+                .map(|d| {
+                    if d == usize::MAX {
+                        f64::INFINITY
+                    } else {
+                        d as f64
+                    }
+                })
+                .collect()
+        };
+        bounding_eccentricities(self.get_num_nodes(), &distances).map(|(ecc, searches)| {
+            summarize(ecc.into_iter().map(|e| e as usize).collect(), searches)
+        })
+    }
+}
+
+impl GeometricGraph {
+    // bounds on the diameter with euclidean edge lengths, dijkstra instead of bfs
+    pub fn weighted_diameter_bounds(
+        &self,
+        budget: Option<Duration>,
+    ) -> Option<DiameterBounds<f64>> {
+        let distances = |s: usize| self.dijkstra_one_to_all(s);
+        ifub(self.graph.get_num_nodes(), &distances, budget)
+    }
+
+    // eccentricities with euclidean edge lengths. center and periphery compare floats exactly,
+    // ties are rare there
+    pub fn weighted_eccentricities(&self) -> Option<Eccentricities<f64>> {
+        if self.graph.get_num_nodes() == 0 {
+            return None;
+        }
+        let distances = |s: usize| self.dijkstra_one_to_all(s);
+        bounding_eccentricities(self.graph.get_num_nodes(), &distances)
+            .map(|(ecc, searches)| summarize(ecc, searches))
+    }
+}
+
+// radius / diameter and searches per node of germany and synthetic models of the same size.
+// a radius near diameter / 2 means a pronounced center, the hop eccentricities are written to
+// output/eccentricity/{name}
+pub fn compare_centralization() -> Vec<(&'static str, f64, f64)> {
+    let germany = example::geometric_germany();
+    let n = germany.graph.get_num_nodes();
+    let models = [
+        ("germany", germany),
+        ("delaunay", delaunay::delaunay(n)),
+        ("gabriel", gabriel_graph::gabriel_graph(n)),
+        (
+            "relative_neighborhood",
+            relative_neighborhood::relative_neighborhood(n),
+        ),
+        ("noise", noise::noise(n)),
+    ];
+    let dir = Config::get().output("eccentricity");
+
+    let mut res = vec![];
+    for (name, g) in models {
+        let g = g.largest_connected_component();
+        let ecc = g.graph.eccentricities().unwrap();
+        let ratio = ecc.radius as f64 / ecc.diameter as f64;
+        let searches = ecc.searches as f64 / g.graph.get_num_nodes() as f64;
+        println!(
+            "{}\t{}: radius {}, diameter {}, {} center nodes, {:.4} searches per node",
+            Local::now(),
+            name,
+            ecc.radius,
+            ecc.diameter,
+            ecc.center.len(),
+            searches
+        );
+        fs::write(
+            dir.join(name),
+            ecc.eccentricity
+                .iter()
+                .map(|e| format!("{}\n", e))
+                .collect::<String>(),
+        )
+        .unwrap();
+        res.push((name, ratio, searches));
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::{delaunay, grid, tree};
+
+    #[test]
+    fn exact_hop_diameter() {
+        let graphs = [
+            grid::generate_grid(15),
+            tree::random_tree(500),
+            delaunay::delaunay(1_000).graph,
+        ];
+        for g in graphs {
+            let bounds = g.diameter_bounds(None).unwrap();
+            assert!(bounds.exact);
+            assert_eq!(bounds.lower, bounds.upper);
+            assert_eq!(bounds.lower, g.get_hop_diameter_primitive());
+        }
+        assert_eq!(grid::generate_grid(15).diameter_ifub(), Some(28));
+
+        let mut disconnected = grid::generate_grid(3);
+        disconnected.add_node();
+        assert_eq!(disconnected.diameter_ifub(), None);
+    }
+
+    #[test]
+    fn exact_weighted_diameter() {
+        let g = delaunay::delaunay(500);
+        let brute_force = g
+            .graph
+            .nodes_iter()
+            .map(|u| eccentricity(&g.dijkstra_one_to_all(u)))
+            .fold(0.0, f64::max);
+        let bounds = g.weighted_diameter_bounds(None).unwrap();
+        assert!(bounds.exact);
+        assert!((bounds.lower - brute_force).abs() < 1e-9);
+    }
+
+    #[test]
+    fn budget_keeps_bounds_valid() {
+        let g = tree::random_tree(2_000);
+        let exact = g.get_hop_diameter_primitive();
+        let bounds = g.diameter_bounds(Some(Duration::ZERO)).unwrap();
+        assert!(bounds.lower <= exact && exact <= bounds.upper);
+    }
+
+    #[test]
+    fn eccentricities_match_bfs() {
+        for g in [tree::random_tree(300), delaunay::delaunay(500).graph] {
+            let ecc = g.eccentricities().unwrap();
+            for u in g.nodes_iter() {
+                assert_eq!(ecc.eccentricity[u], *g.bfs(u).iter().max().unwrap());
+            }
+            assert_eq!(ecc.diameter, g.get_hop_diameter_primitive());
+            assert!(ecc
+                .center
+                .iter()
+                .all(|&u| ecc.eccentricity[u] == ecc.radius));
+            assert!(ecc.searches <= g.get_num_nodes());
+        }
+
+        // odd path: one center, both ends are the periphery
+        let path = Graph::from_edge_list((0..6).map(|i| (i, i + 1)).collect());
+        let ecc = path.eccentricities().unwrap();
+        assert_eq!((ecc.radius, ecc.diameter), (3, 6));
+        assert_eq!(ecc.center, vec![3]);
+        assert_eq!(ecc.periphery, vec![0, 6]);
+    }
+
+    #[test]
+    fn weighted_eccentricities_match_dijkstra() {
+        let g = delaunay::delaunay(300);
+        let ecc = g.weighted_eccentricities().unwrap();
+        for u in g.graph.nodes_iter() {
+            let exact = eccentricity(&g.dijkstra_one_to_all(u));
+            assert!((ecc.eccentricity[u] - exact).abs() < 1e-9);
+        }
+        assert!(ecc.radius <= ecc.diameter && ecc.diameter <= 2.0 * ecc.radius + 1e-9);
+    }
+}
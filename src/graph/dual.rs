@@ -0,0 +1,245 @@
+use chrono::Local;
+use geo::Point;
+use hashbrown::{HashMap, HashSet};
+use ordered_float::OrderedFloat;
+
+use std::fs;
+use std::io;
+
+use super::faces::Faces;
+use super::geometric_graph::GeometricGraph;
+use super::planar::planarize;
+use super::{example, noise, Graph};
+use crate::dataset::Config;
+use crate::separator::Mode;
+
+// inner faces with more than this many times the median inner face area are barriers
+pub const BARRIER_FACTOR: f64 = 20.0;
+
+// face adjacency graph, node i is faces.faces[i]. faces sharing several edges get one edge, a
+// bridge borders the same face on both sides and gives no edge
+#[derive(Debug, Clone)]
+pub struct Dual {
+    pub graph: GeometricGraph,
+    pub faces: Faces,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarrierStats {
+    pub barriers: usize,
+    // share of the inner face area covered by barrier faces
+    pub barrier_area: f64,
+    // share of the separator nodes on the boundary of a barrier face, and of all nodes
+    pub separator_contact: f64,
+    pub node_contact: f64,
+}
+
+impl GeometricGraph {
+    // area centroid of a face, the mean of its walk for faces without area
+    fn face_centroid(&self, nodes: &[usize]) -> Point {
+        let (mut cx, mut cy, mut twice_area) = (0.0, 0.0, 0.0);
+        for (i, &u) in nodes.iter().enumerate() {
+            let p = self.get_position(u);
+            let q = self.get_position(nodes[(i + 1) % nodes.len()]);
+            let cross = p.x() * q.y() - q.x() * p.y();
+            cx += (p.x() + q.x()) * cross;
+            cy += (p.y() + q.y()) * cross;
+            twice_area += cross;
+        }
+        if twice_area.abs() < 1e-12 {
+            let k = nodes.len() as f64;
+            let x = nodes.iter().map(|&u| self.get_position(u).x()).sum::<f64>();
+            let y = nodes.iter().map(|&u| self.get_position(u).y()).sum::<f64>();
+            return Point::new(x / k, y / k);
+        }
+        Point::new(cx / (3.0 * twice_area), cy / (3.0 * twice_area))
+    }
+
+    // dual of a planar straight line drawing with the face centroids as positions. the outer
+    // face sits at the centroid of the hull, inside the drawing
+    pub fn dual(&self) -> Dual {
+        let faces = self.faces();
+        let mut face_of_dart = HashMap::new();
+        for (i, face) in faces.faces.iter().enumerate() {
+            for (j, &u) in face.nodes.iter().enumerate() {
+                face_of_dart.insert((u, face.nodes[(j + 1) % face.nodes.len()]), i);
+            }
+        }
+
+        let mut graph = Graph::with_node_count(faces.faces.len());
+        for (&(u, v), &f) in &face_of_dart {
+            let g = face_of_dart[&(v, u)];
+            if f != g {
+                graph.add_directed_edge(f, g);
+            }
+        }
+        let positions = faces
+            .faces
+            .iter()
+            .map(|f| self.face_centroid(&f.nodes))
+            .collect();
+        Dual {
+            graph: GeometricGraph::new(graph, positions),
+            faces,
+        }
+    }
+}
+
+impl Dual {
+    // inner faces much larger than the typical face, lakes, forests and the holes noise leaves
+    pub fn barrier_faces(&self, factor: f64) -> Vec<usize> {
+        let mut areas = self.faces.inner().map(|f| f.area).collect::<Vec<_>>();
+        if areas.is_empty() {
+            return vec![];
+        }
+        areas.sort_unstable_by_key(|&a| OrderedFloat(a));
+        let median = areas[areas.len() / 2];
+        (0..self.faces.faces.len())
+            .filter(|i| !self.faces.outer.contains(i))
+            .filter(|&i| self.faces.faces[i].area > factor * median)
+            .collect()
+    }
+
+    // how much of the area barriers take and whether the separator runs along them
+    pub fn barrier_stats(&self, separator: &HashSet<usize>, factor: f64) -> BarrierStats {
+        let barriers = self.barrier_faces(factor);
+        let on_barrier = barriers
+            .iter()
+            .flat_map(|&i| self.faces.faces[i].nodes.iter().copied())
+            .collect::<HashSet<_>>();
+        let total_area = self.faces.inner().map(|f| f.area).sum::<f64>();
+        let barrier_area = barriers
+            .iter()
+            .map(|&i| self.faces.faces[i].area)
+            .sum::<f64>();
+        let nodes = self
+            .faces
+            .faces
+            .iter()
+            .flat_map(|f| f.nodes.iter())
+            .collect::<HashSet<_>>()
+            .len();
+
+        BarrierStats {
+            barriers: barriers.len(),
+            barrier_area: barrier_area / total_area.max(f64::MIN_POSITIVE),
+            separator_contact: separator.iter().filter(|u| on_barrier.contains(*u)).count() as f64
+                / separator.len().max(1) as f64,
+            node_contact: on_barrier.len() as f64 / nodes.max(1) as f64,
+        }
+    }
+}
+
+// separator size against barrier statistics for germany and noise graphs with growing scale
+// sets, written as "name n separator barriers area separator_contact node_contact" to
+// output/dual/barriers
+pub fn barrier_overview() -> io::Result<()> {
+    let germany = example::geometric_germany();
+    let n = germany.graph.get_num_nodes();
+    let mut models = vec![("germany".to_string(), germany)];
+    for k in 1..=noise::NOISE_SCALES.len() {
+        models.push((
+            format!("noise_{k}"),
+            noise::noise_scales(n, &noise::NOISE_SCALES[..k]),
+        ));
+    }
+
+    let mut out = String::new();
+    for (name, g) in models {
+        let mut planar = g.clone();
+        if planar.count_crossings() > 0 {
+            planarize(&mut planar);
+        }
+        let separator = g.graph.get_separator_wrapper(Mode::Native);
+        let stats = planar.dual().barrier_stats(&separator, BARRIER_FACTOR);
+        println!(
+            "{}\t{}: |S| = {}, {:?}",
+            Local::now(),
+            name,
+            separator.len(),
+            stats
+        );
+        out.push_str(&format!(
+            "{} {} {} {} {} {} {}\n",
+            name,
+            g.graph.get_num_nodes(),
+            separator.len(),
+            stats.barriers,
+            stats.barrier_area,
+            stats.separator_contact,
+            stats.node_contact
+        ));
+    }
+    fs::write(Config::get().output("dual").join("barriers"), out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::graph::delaunay;
+    use crate::graph::example::geometric_grid;
+
+    #[test]
+    fn dual_of_grid() {
+        let dual = geometric_grid(4).dual();
+        // 9 cells and the outer face
+        assert_eq!(dual.graph.graph.get_num_nodes(), 10);
+        let outer = dual.faces.outer[0];
+        assert_eq!(dual.graph.graph.degree(outer), 8);
+        // the middle cell touches four cells
+        let middle = (0..10)
+            .find(|&i| i != outer && dual.graph.get_position(i) == Point::new(1.5, 1.5))
+            .unwrap();
+        assert_eq!(dual.graph.graph.degree(middle), 4);
+        assert_eq!(dual.graph.get_position(outer), Point::new(1.5, 1.5));
+    }
+
+    #[test]
+    fn dual_of_delaunay() {
+        let g = delaunay::delaunay(200);
+        let dual = g.dual();
+        let outer = dual.faces.outer[0];
+        let triangles = dual.graph.graph.nodes_iter().filter(|&i| i != outer);
+        assert!(triangles.clone().all(|i| dual.graph.graph.degree(i) <= 3));
+        // every edge off the hull separates two triangles
+        let inner_edges = triangles
+            .map(|i| {
+                dual.graph
+                    .graph
+                    .get_neighbors(i)
+                    .iter()
+                    .filter(|&&j| j != outer)
+                    .count()
+            })
+            .sum::<usize>()
+            / 2;
+        let hull = dual.faces.faces[outer].degree();
+        assert_eq!(inner_edges, g.graph.get_num_edges() - hull);
+    }
+
+    #[test]
+    fn hole_is_a_barrier() {
+        // 10x10 grid with the 3x3 block of cells around the four middle nodes merged into one
+        // face by removing these nodes
+        let mut g = geometric_grid(11);
+        for x in 5..=6 {
+            for y in 5..=6 {
+                g.graph.clear_vertex_edges(x * 11 + y);
+            }
+        }
+        let g = g.largest_connected_component();
+        let dual = g.dual();
+        let barriers = dual.barrier_faces(BARRIER_FACTOR);
+        assert_eq!(barriers.len(), 0);
+        let barriers = dual.barrier_faces(8.0);
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(dual.faces.faces[barriers[0]].area, 9.0);
+
+        let all = g.graph.nodes_iter().collect::<HashSet<_>>();
+        let stats = dual.barrier_stats(&all, 8.0);
+        assert_eq!(stats.barriers, 1);
+        assert!((stats.barrier_area - 9.0 / 100.0).abs() < 1e-12);
+        assert_eq!(stats.separator_contact, stats.node_contact);
+    }
+}
@@ -1,10 +1,10 @@
-use std::path::Path;
-
 use geo::Point;
 
-use crate::{graph::Graph, library};
+use crate::dataset::Dataset;
+use crate::graph::Graph;
 
 use super::geometric_graph::GeometricGraph;
+use super::grid;
 
 pub const DEGREE_DISTRIBUTION_GER: [f64; 10] = [
     0.0,
@@ -61,42 +61,58 @@ pub fn example_c4() -> GeometricGraph {
     GeometricGraph::new(g, positions)
 }
 
+// unit grid, node i * side + j at (i, j)
+pub fn geometric_grid(side: usize) -> GeometricGraph {
+    let positions = (0..side * side)
+        .map(|u| Point::new((u / side) as f64, (u % side) as f64))
+        .collect();
+    GeometricGraph::new(grid::generate_grid(side), positions)
+}
+
+// straight path, node i at (i, 0)
+pub fn geometric_path(n: usize) -> GeometricGraph {
+    GeometricGraph::new(
+        Graph::from_edge_list((0..n - 1).map(|i| (i, i + 1)).collect()),
+        (0..n).map(|i| Point::new(i as f64, 0.0)).collect(),
+    )
+}
+
 pub fn karlsruhe() -> Graph {
-    Graph::from_file(Path::new("../Graphs/karlsruhe-connected-bidirectional")).unwrap()
+    Dataset::load("karlsruhe").unwrap()
 }
 
 pub fn germany() -> Graph {
-    Graph::from_file(Path::new("../Graphs/germany-connected-bidirectional")).unwrap()
+    Dataset::load("germany").unwrap()
 }
 
 pub fn europe() -> Graph {
-    Graph::from_file(Path::new("../Graphs/europe-connected-bidirectional")).unwrap()
+    Dataset::load("europe").unwrap()
 }
 
 pub fn geometric_karlsruhe() -> GeometricGraph {
-    GeometricGraph::from_file(Path::new("../Graphs/karlsruhe-connected-bidirectional")).unwrap()
+    Dataset::load_geometric("karlsruhe").unwrap()
 }
 
 pub fn geometric_germany() -> GeometricGraph {
-    GeometricGraph::from_file(Path::new("../Graphs/germany-connected-bidirectional")).unwrap()
+    Dataset::load_geometric("germany").unwrap()
 }
 
 pub fn geometric_europe() -> GeometricGraph {
-    GeometricGraph::from_file(Path::new("../Graphs/europe-connected-bidirectional")).unwrap()
+    Dataset::load_geometric("europe").unwrap()
 }
 
 pub fn osm_karlsruhe() -> GeometricGraph {
-    GeometricGraph::from_file(Path::new("../Graphs/karlsruhe-osm-connected-bidirectional")).unwrap()
+    Dataset::load_geometric("karlsruhe-osm").unwrap()
 }
 
 pub fn ord_karlsruhe() -> Vec<usize> {
-    library::read_to_usize_vec(Path::new("./output/ord/karlsruhe"))
+    Dataset::load_order("karlsruhe")
 }
 
 pub fn ord_germany() -> Vec<usize> {
-    library::read_to_usize_vec(Path::new("./output/ord/germany"))
+    Dataset::load_order("germany")
 }
 
 pub fn ord_europe() -> Vec<usize> {
-    library::read_to_usize_vec(Path::new("./output/ord/europe"))
+    Dataset::load_order("europe")
 }
@@ -0,0 +1,270 @@
+use chrono::Local;
+use ordered_float::OrderedFloat;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::geometric_graph::GeometricGraph;
+use super::planar::planarize;
+use super::planarity::Embedding;
+use super::{example, nested_grid, noise, voronoi};
+use crate::dataset::Config;
+
+// faces with at least this many sides share the last bin of the degree distribution
+pub const MAX_FACE_DEGREE: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Face {
+    // boundary walk, a bridge or a dangling path is walked along on both sides
+    pub nodes: Vec<usize>,
+    // area enclosed by the walk. components are not merged, so a face with another component
+    // drawn inside it still counts the area that component covers
+    pub area: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Faces {
+    pub faces: Vec<Face>,
+    // index of the outer face of every connected component
+    pub outer: Vec<usize>,
+    is_outer: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FaceStats {
+    pub inner_faces: usize,
+    pub mean_area: f64,
+    pub median_area: f64,
+    // standard deviation over mean of the inner face areas
+    pub area_variation: f64,
+    // share of the inner faces with 3, 4, ... sides, index = sides
+    pub degree_distribution: Vec<f64>,
+    pub mean_degree: f64,
+    pub outer_degree: usize,
+}
+
+impl Face {
+    pub fn degree(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl GeometricGraph {
+    // shoelace formula, positive for counterclockwise walks
+    fn signed_area(&self, nodes: &[usize]) -> f64 {
+        let mut sum = 0.0;
+        for (i, &u) in nodes.iter().enumerate() {
+            let p = self.get_position(u);
+            let q = self.get_position(nodes[(i + 1) % nodes.len()]);
+            sum += p.x() * q.y() - q.x() * p.y();
+        }
+        sum / 2.0
+    }
+
+    // faces of a planar straight line drawing, from the angular order around every node. the
+    // drawing must not have crossings, see planarize. inner faces are walked clockwise, the
+    // outer face of a component is the only one walked counterclockwise around it
+    pub fn faces(&self) -> Faces {
+        let walks = Embedding::from_geometric(self).faces();
+        let mut component = vec![usize::MAX; self.graph.get_num_nodes()];
+        let mut components = 0;
+        for s in self.graph.nodes_iter() {
+            if component[s] != usize::MAX || self.graph.degree(s) == 0 {
+                continue;
+            }
+            component[s] = components;
+            let mut stack = vec![s];
+            while let Some(u) = stack.pop() {
+                for &v in self.graph.get_neighbors(u) {
+                    if component[v] == usize::MAX {
+                        component[v] = components;
+                        stack.push(v);
+                    }
+                }
+            }
+            components += 1;
+        }
+
+        // the outer walk has the largest counterclockwise area of its component, for trees all
+        // areas are 0 and the single walk is outer
+        let signed = walks
+            .iter()
+            .map(|w| self.signed_area(w))
+            .collect::<Vec<_>>();
+        let mut outer = vec![usize::MAX; components];
+        for (i, w) in walks.iter().enumerate() {
+            let c = component[w[0]];
+            if outer[c] == usize::MAX || signed[i] > signed[outer[c]] {
+                outer[c] = i;
+            }
+        }
+
+        let mut is_outer = vec![false; walks.len()];
+        for &i in &outer {
+            is_outer[i] = true;
+        }
+        Faces {
+            faces: walks
+                .into_iter()
+                .zip(signed)
+                .map(|(nodes, area)| Face {
+                    nodes,
+                    area: area.abs(),
+                })
+                .collect(),
+            outer,
+            is_outer,
+        }
+    }
+
+    // faces of the drawing with every crossing turned into a node first
+    pub fn planarized_faces(&self) -> Faces {
+        if self.count_crossings() == 0 {
+            return self.faces();
+        }
+        let mut planar = self.clone();
+        planarize(&mut planar);
+        planar.faces()
+    }
+}
+
+impl Faces {
+    pub fn inner(&self) -> impl Iterator<Item = &Face> {
+        self.faces
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.is_outer[i])
+            .map(|(_, f)| f)
+    }
+
+    pub fn degree_distribution(&self) -> Vec<f64> {
+        let mut distribution = [0.0; MAX_FACE_DEGREE + 1];
+        let mut count = 0;
+        for f in self.inner() {
+            distribution[f.degree().min(MAX_FACE_DEGREE)] += 1.0;
+            count += 1;
+        }
+        distribution
+            .iter()
+            .map(|&c| c / count.max(1) as f64)
+            .collect()
+    }
+
+    pub fn stats(&self) -> FaceStats {
+        let mut areas = self.inner().map(|f| f.area).collect::<Vec<_>>();
+        areas.sort_unstable_by_key(|&a| OrderedFloat(a));
+        let k = areas.len().max(1) as f64;
+        let mean_area = areas.iter().sum::<f64>() / k;
+        let variance = areas.iter().map(|a| (a - mean_area).powi(2)).sum::<f64>() / k;
+        FaceStats {
+            inner_faces: areas.len(),
+            mean_area,
+            median_area: areas.get(areas.len() / 2).copied().unwrap_or(0.0),
+            area_variation: if mean_area > 0.0 {
+                variance.sqrt() / mean_area
+            } else {
+                0.0
+            },
+            degree_distribution: self.degree_distribution(),
+            mean_degree: self.inner().map(|f| f.degree()).sum::<usize>() as f64 / k,
+            outer_degree: self.outer.iter().map(|&i| self.faces[i].degree()).sum(),
+        }
+    }
+
+    // "area degree" per inner face
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        fs::write(
+            file,
+            self.inner()
+                .map(|f| format!("{} {}\n", f.area, f.degree()))
+                .collect::<String>(),
+        )
+    }
+}
+
+// face statistics of germany and the voronoi, nested grid and noise generators, the faces are
+// written to output/faces/{name}
+pub fn compare_generators() -> io::Result<Vec<(&'static str, FaceStats)>> {
+    let germany = example::geometric_germany();
+    let n = germany.graph.get_num_nodes();
+    let models = [
+        ("germany", germany),
+        ("voronoi", voronoi::voronoi_example_small()),
+        ("nested_grid", nested_grid::build_nested_grid(5, 5)),
+        ("noise", noise::noise(n)),
+    ];
+    let dir = Config::get().output("faces");
+
+    let mut res = vec![];
+    for (name, g) in models {
+        let faces = g.planarized_faces();
+        let stats = faces.stats();
+        println!("{}\t{}: {:?}", Local::now(), name, stats);
+        faces.save(&dir.join(name))?;
+        res.push((name, stats));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geo::Point;
+
+    use crate::graph::{delaunay, Graph};
+
+    #[test]
+    fn grid_faces() {
+        let faces = example::geometric_grid(5).faces();
+        assert_eq!(faces.faces.len(), 17);
+        assert_eq!(faces.outer.len(), 1);
+        let outer = &faces.faces[faces.outer[0]];
+        assert_eq!(outer.degree(), 16);
+        assert_eq!(outer.area, 16.0);
+
+        let stats = faces.stats();
+        assert_eq!(stats.inner_faces, 16);
+        assert_eq!(stats.mean_area, 1.0);
+        assert_eq!(stats.area_variation, 0.0);
+        assert_eq!(stats.degree_distribution[4], 1.0);
+    }
+
+    #[test]
+    fn delaunay_areas_add_up() {
+        let g = delaunay::delaunay(300);
+        let faces = g.faces();
+        let inner = faces.inner().map(|f| f.area).sum::<f64>();
+        let hull = faces.faces[faces.outer[0]].area;
+        assert!((inner - hull).abs() < 1e-9 * hull);
+        assert_eq!(faces.stats().degree_distribution[3], 1.0);
+        // euler: f = m - n + 2
+        assert_eq!(
+            faces.faces.len(),
+            g.graph.get_num_edges() - g.graph.get_num_nodes() + 2
+        );
+    }
+
+    #[test]
+    fn bridge_and_two_components() {
+        // a triangle with a pendant edge, and a separate edge
+        let g = GeometricGraph::new(
+            Graph::from_edge_list(vec![(0, 1), (1, 2), (2, 0), (2, 3), (4, 5)]),
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 0.0),
+                Point::new(0.0, 2.0),
+                Point::new(-1.0, 3.0),
+                Point::new(5.0, 5.0),
+                Point::new(6.0, 5.0),
+            ],
+        );
+        let faces = g.faces();
+        assert_eq!(faces.outer.len(), 2);
+        let stats = faces.stats();
+        assert_eq!(stats.inner_faces, 1);
+        assert_eq!(stats.mean_area, 2.0);
+        // the pendant edge is walked on both sides of the outer face
+        assert_eq!(stats.outer_degree, 5 + 2);
+    }
+}
@@ -22,8 +22,12 @@ use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+use crate::container::{self, Attributes, GraphFile};
+use crate::dataset::Config;
 use crate::graph::planar::planarize;
+use crate::graph::projection::{Crs, Projection};
 use crate::library;
+use crate::mmap::MappedSlice;
 use crate::Graph;
 use ordered_float::OrderedFloat;
 use rand::{thread_rng, Rng};
@@ -32,6 +36,8 @@ use rand::{thread_rng, Rng};
 pub struct GeometricGraph {
     pub graph: Graph,
     pub positions: Vec<Point>,
+    // how the positions are to be read, None for generated graphs
+    pub crs: Option<Crs>,
 }
 
 const QUANTIZE_SCALE: f64 = 1e12;
@@ -67,28 +73,40 @@ pub fn approx_dedup_edges(edges: &mut Vec<(Point, Point)>) {
 }
 
 pub fn karlsruhe_bounding_rect() -> Rect {
-    let min_point = Point::new(48.3, 8.0);
-    let max_point = Point::new(49.2, 9.0);
+    let min_point = Point::new(8.0, 48.3);
+    let max_point = Point::new(9.0, 49.2);
     Rect::new(min_point, max_point)
 }
 
 impl GeometricGraph {
     pub fn new(graph: Graph, positions: Vec<Point>) -> GeometricGraph {
         assert_eq!(graph.get_num_nodes(), positions.len());
-        GeometricGraph { graph, positions }
+        GeometricGraph {
+            graph,
+            positions,
+            crs: None,
+        }
     }
 
-    pub fn from_file(dir: &Path) -> io::Result<Self> {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            return GeometricGraph::from_routingkit(path);
+        }
+        let file = GraphFile::open(path)?;
+        let positions = file.get_positions().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "graph file has no positions")
+        })?;
+        let mut g = GeometricGraph::new(file.csr.to_graph(), positions);
+        g.crs = file.crs;
+        Ok(g)
+    }
+
+    // positions are x = longitude, y = latitude as in from_osm_xml
+    pub fn from_routingkit(dir: &Path) -> io::Result<Self> {
         let g = Graph::from_file(dir)?;
 
-        let latitudes = library::read_binary_vec::<f32>(&dir.join("latitude"))?
-            .par_iter()
-            .map(|&f| f as f64)
-            .collect::<Vec<_>>();
-        let longitudes = library::read_binary_vec::<f32>(&dir.join("longitude"))?
-            .par_iter()
-            .map(|&f| f as f64)
-            .collect::<Vec<_>>();
+        let latitudes = MappedSlice::<f32>::open(&dir.join("latitude"))?;
+        let longitudes = MappedSlice::<f32>::open(&dir.join("longitude"))?;
 
         assert_eq!(g.get_num_nodes(), latitudes.len());
         assert_eq!(g.get_num_nodes(), longitudes.len());
@@ -96,9 +114,35 @@ impl GeometricGraph {
         let positions = latitudes
             .par_iter()
             .zip(longitudes.par_iter())
-            .map(|(&lat, &lon)| Point::new(lat, lon))
+            .map(|(&lat, &lon)| Point::new(lon as f64, lat as f64))
             .collect();
-        Ok(GeometricGraph::new(g, positions))
+        let mut g = GeometricGraph::new(g, positions);
+        g.crs = Some(Crs::Wgs84);
+        Ok(g)
+    }
+
+    // reads wgs84 coordinates and projects them to meters
+    pub fn from_file_projected(dir: &Path, projection: Projection) -> io::Result<Self> {
+        let mut g = GeometricGraph::from_file(dir)?;
+        g.project(projection);
+        Ok(g)
+    }
+
+    pub fn project(&mut self, projection: Projection) {
+        assert_ne!(
+            self.crs,
+            Some(Crs::Planar),
+            "positions are already projected"
+        );
+        self.positions
+            .par_iter_mut()
+            .for_each(|p| *p = projection.project(*p));
+        self.crs = Some(Crs::Planar);
+    }
+
+    // transverse mercator around the data, the positions have to be wgs84
+    pub fn project_local(&mut self) {
+        self.project(Projection::local(&self.positions));
     }
 
     pub fn get_edges_points(&self) -> Vec<(Point, Point)> {
@@ -168,13 +212,25 @@ impl GeometricGraph {
         g
     }
 
-    pub fn save(&self, dir: &Path) -> io::Result<()> {
-        self.graph.save(dir)?;
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        let attributes = Attributes {
+            crs: self.crs,
+            ..Default::default()
+        };
+        self.save_with_attributes(file, &attributes)
+    }
+
+    pub fn save_with_attributes(&self, file: &Path, attributes: &Attributes) -> io::Result<()> {
+        container::write(file, &self.graph, Some(&self.positions), attributes)
+    }
+
+    pub fn save_routingkit(&self, dir: &Path) -> io::Result<()> {
+        self.graph.save_routingkit(dir)?;
         library::write_binary_vec(
             &self
                 .positions
                 .iter()
-                .map(|p| p.x() as f32)
+                .map(|p| p.y() as f32)
                 .collect::<Vec<f32>>(),
             &dir.join("latitude"),
         )?;
@@ -182,7 +238,7 @@ impl GeometricGraph {
             &self
                 .positions
                 .iter()
-                .map(|p| p.y() as f32)
+                .map(|p| p.x() as f32)
                 .collect::<Vec<f32>>(),
             &dir.join("longitude"),
         )
@@ -201,6 +257,11 @@ impl GeometricGraph {
         Euclidean::distance(self.get_position(u), self.get_position(v))
     }
 
+    // distance in meters for unprojected wgs84 positions
+    pub fn haversine_distance(&self, u: usize, v: usize) -> f64 {
+        Crs::Wgs84.distance(self.get_position(u), self.get_position(v))
+    }
+
     pub fn save_edge_length_overview(&self, file: &Path) {
         fs::write(
             file,
@@ -232,7 +293,9 @@ impl GeometricGraph {
             }
         }
 
-        GeometricGraph::new(Graph::new(data), positions)
+        let mut llc = GeometricGraph::new(Graph::new(data), positions);
+        llc.crs = self.crs;
+        llc
     }
 
     pub fn get_edge_lengths(&self) -> HashMap<(usize, usize), f64> {
@@ -243,6 +306,17 @@ impl GeometricGraph {
             .collect()
     }
 
+    pub fn get_edge_lengths_crs(&self, crs: Crs) -> HashMap<(usize, usize), f64> {
+        self.graph
+            .get_edges()
+            .into_par_iter()
+            .map(|(u, v)| {
+                let (p, q) = (self.get_position(u), self.get_position(v));
+                ((u, v), crs.geodesic_distance(p, q))
+            })
+            .collect()
+    }
+
     pub fn get_edge_lengths_unidirectional(&self) -> HashMap<(usize, usize), f64> {
         self.graph
             .get_edges()
@@ -512,21 +586,21 @@ impl GeometricGraph {
     }
 
     pub fn visualize_igraph(&self, name: &str) {
-        let g_path = format!("./output/graphs/{name}");
-        self.save(Path::new(&g_path));
+        let g_path = Config::get().output("graphs").join(name);
+        self.save_routingkit(&g_path);
 
         Command::new("python3")
-            .arg("./scripts/visualize_igraph.py")
+            .arg(Config::get().script("visualize_igraph.py"))
             .arg(g_path)
             .spawn();
     }
 
     pub fn visualize(&self, name: &str) {
-        let g_path = format!("./output/graphs/{}", name);
-        self.save(Path::new(&g_path));
+        let g_path = Config::get().output("graphs").join(name);
+        self.save_routingkit(&g_path);
 
         Command::new("python3")
-            .arg("scripts/visualize_graph.py")
+            .arg(Config::get().script("visualize_graph.py"))
             .arg(g_path)
             .spawn();
     }
@@ -553,4 +627,32 @@ mod test {
         assert!(!g.dijkstra_less_than(0, 2, 1.4, &edge_lengths));
         assert!(g.dijkstra_less_than(0, 2, 2.00001, &edge_lengths));
     }
+
+    #[test]
+    fn save_load_axis_order() {
+        let mut g = example::example_c4();
+        g.positions = vec![
+            Point::new(8.40, 49.00),
+            Point::new(8.40, 49.01),
+            Point::new(8.41, 49.01),
+            Point::new(8.41, 49.00),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        g.save_routingkit(dir.path()).unwrap();
+
+        let latitude = library::read_binary_vec::<f32>(&dir.path().join("latitude")).unwrap();
+        assert_eq!(latitude[1], 49.01);
+        let loaded = GeometricGraph::from_file(dir.path()).unwrap();
+        assert!((loaded.get_position(2).x() - 8.41).abs() < 1e-5);
+        assert_eq!(loaded.crs, Some(Crs::Wgs84));
+
+        // 0.01° of latitude are about 1.1km
+        let lengths = loaded.get_edge_lengths_crs(Crs::Wgs84);
+        assert!((lengths[&(0, 1)] - 1112.0).abs() < 5.0);
+        let projection = Projection::local(&g.positions);
+        let projected = GeometricGraph::from_file_projected(dir.path(), projection).unwrap();
+        assert_eq!(projected.crs, Some(Crs::Planar));
+        let projected = projected.get_edge_lengths();
+        assert!((projected[&(0, 1)] - lengths[&(0, 1)]).abs() < 2.0);
+    }
 }
@@ -100,19 +100,23 @@ pub fn example1() -> GeometricGraph {
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::path::Path;
 
     use geo::Point;
 
+    use crate::dataset::Config;
     use crate::library;
 
     #[test]
     fn length_overview() {
-        let points = library::random_points_in_circle(Point::new(100_000.0, 100_000.0), 100.0, 10000);
-        let edges = delaunay::dynamic_length_restriced_delaunay(&points, 0.95);
+        let points =
+            library::random_points_in_circle(Point::new(100_000.0, 100_000.0), 100.0, 10000);
+        let g = delaunay::dynamic_length_restriced_delaunay(points, 0.95);
         //let edges = delaunay::delaunay_edges(&points);
-        let g = GeometricGraph::from_edges_point(&edges);
-        g.save_edge_length_overview(Path::new("output/disks_length_overview.txt"));
+        g.save_edge_length_overview(
+            &Config::get()
+                .output("edge_lengths")
+                .join("disks_length_overview.txt"),
+        );
     }
 
     #[test]
@@ -123,7 +127,7 @@ mod test {
 
         let g = super::generate_circle_center_graph_v2(&points_per_level, &city_percentage, &radii);
         g.graph.info();
-        g.save(Path::new("output/graphs/disks"));
+        g.save_routingkit(&Config::get().output("graphs").join("disks"));
 
         //println!(""g.graph.get_separator_size(crate::separator::Mode::Fast));
         //g.graph.recurse_separator(crate::separator::Mode::Fast, None);
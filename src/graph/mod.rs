@@ -25,12 +25,18 @@ use std::vec;
 use std::{collections::BTreeSet, fs, io, path::Path};
 use tempfile::NamedTempFile;
 
+use crate::container::{self, Attributes};
+use crate::dataset::Config;
+use crate::mmap::CsrGraph;
 use crate::{library, separator};
 pub mod cbrt_bridged;
 pub mod cbrt_grid;
 pub mod cbrt_maximal;
 pub mod delaunay;
+pub mod diameter;
+pub mod dual;
 pub mod example;
+pub mod faces;
 pub mod gabriel_graph;
 pub mod geometric_graph;
 pub mod grid;
@@ -42,6 +48,8 @@ pub mod nested_grid;
 pub mod nested_sparse;
 pub mod noise;
 pub mod planar;
+pub mod planarity;
+pub mod projection;
 pub mod relative_neighborhood;
 pub mod tree;
 pub mod unit_disk;
@@ -145,39 +153,22 @@ impl Graph {
         Ok(Graph::from_edge_list(edges))
     }
 
-    pub fn from_file(dir: &Path) -> io::Result<Self> {
-        let mut xadj = library::read_to_usize_vec(&dir.join("first_out"));
-        xadj.pop();
-        let adjncy = library::read_to_usize_vec(&dir.join("head"));
-
-        let data: Vec<HashSet<_>> = xadj
-            .par_iter()
-            .enumerate()
-            .map(|(i, &start)| {
-                let end = xadj.get(i + 1).copied().unwrap_or(adjncy.len());
-                adjncy[start..end].iter().copied().collect()
-            })
-            .collect();
-
-        Ok(Graph { data })
+    // either a graph file written by save or a RoutingKit directory. builds the adjacency sets,
+    // code that only iterates neighbors should use CsrGraph::from_file instead
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Ok(CsrGraph::from_file(path)?.to_graph())
     }
 
-    pub fn from_file_directed(dir: &Path) -> io::Result<Self> {
-        let xadj = library::read_to_usize_vec(&dir.join("first_out"));
-        let adjncy = library::read_to_usize_vec(&dir.join("head"));
-
-        let mut g = Graph::with_node_count(xadj.len() - 1);
-
-        for i in 0..xadj.len() - 1 {
-            for j in xadj[i]..xadj[i + 1] {
-                g.add_directed_edge(i, adjncy[j]);
-            }
-        }
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        container::write(file, self, None, &Attributes::default())
+    }
 
-        Ok(g)
+    pub fn save_with_attributes(&self, file: &Path, attributes: &Attributes) -> io::Result<()> {
+        container::write(file, self, None, attributes)
     }
 
-    pub fn save(&self, dir: &Path) -> io::Result<()> {
+    // first_out and head as read by RoutingKit and the InertialFlowCutter
+    pub fn save_routingkit(&self, dir: &Path) -> io::Result<()> {
         fs::create_dir_all(dir)?;
         let (xadj, adjncy) = self.get_adjacency_array();
         library::write_binary_vec(&xadj, &dir.join("first_out"))?;
@@ -769,11 +760,11 @@ impl Graph {
             return;
         }
 
-        let g_path = format!("./output/graphs/{}", name);
-        self.save(Path::new(&g_path));
+        let g_path = Config::get().output("graphs").join(name);
+        self.save_routingkit(&g_path);
 
         Command::new("python3")
-            .arg("scripts/visualize_graph.py")
+            .arg(Config::get().script("visualize_graph.py"))
             .arg("--auto-layout")
             .arg(g_path)
             .spawn();
@@ -784,7 +775,7 @@ impl Graph {
         self.save_metis(f.path());
 
         Command::new("python3")
-            .arg("scripts/visualize_metis.py")
+            .arg(Config::get().script("visualize_metis.py"))
             .arg(f.path())
             .spawn()
             .expect("Failed to execute command")
@@ -821,117 +812,11 @@ impl Graph {
         );
     }
 
-    fn get_path(&self, start: usize, end: usize) -> Vec<usize> {
-        let distances = self.bfs(start);
-        let mut path = Vec::new();
-        if distances[end] == usize::MAX {
-            return path;
-        }
-
-        let mut curr = end;
-        path.push(curr);
-
-        while curr != start {
-            for &neighbor in self.get_neighbors(curr) {
-                if distances[neighbor] == distances[curr] - 1 {
-                    curr = neighbor;
-                    path.push(curr);
-                    break;
-                }
-            }
-        }
-        path.reverse();
-        path
-    }
-
-    fn get_midpoint(&self, start: usize, end: usize) -> usize {
-        let path = self.get_path(start, end);
-        if path.is_empty() {
-            return start;
-        }
-        path[path.len() / 2]
-    }
-
     pub fn meshedness_coefficient(&self) -> f64 {
         let m = self.get_num_edges() as f64;
         let n = self.get_num_nodes() as f64;
         (m - n + 1.) / (2. * n - 5.)
     }
-
-    pub fn diameter_ifub(&self) -> Option<usize> {
-        if self.get_num_nodes() <= 1 {
-            return Some(0);
-        }
-
-        let (r1, _) = self.get_furthest_node(0);
-        if self.get_degree(r1) == 0 {
-            return None;
-        }
-        let (a1, ecc_a1) = self.get_furthest_node(r1);
-        let (b1, _) = self.get_furthest_node(a1);
-        let r2 = self.get_midpoint(a1, b1);
-        let (a2, ecc_a2) = self.get_furthest_node(r2);
-        let (b2, _) = self.get_furthest_node(a2);
-        let u = self.get_midpoint(a2, b2);
-
-        let initial_lb = ecc_a1.max(ecc_a2);
-
-        let dists_from_u = self.bfs(u);
-        let ecc_u = match dists_from_u.iter().filter(|&&d| d != usize::MAX).max() {
-            Some(&max_dist) => max_dist,
-            None => return None,
-        };
-
-        if dists_from_u.iter().any(|&d| d == usize::MAX) {
-            return None;
-        }
-
-        let mut lb = initial_lb.max(ecc_u);
-        let mut ub = 2 * ecc_u;
-
-        if ub <= lb {
-            return Some(lb);
-        }
-
-        let mut nodes_by_level: Vec<Vec<usize>> = vec![Vec::new(); ecc_u + 1];
-        for (node, &dist) in dists_from_u.iter().enumerate() {
-            nodes_by_level[dist].push(node);
-        }
-
-        // warning: this is just an approximation (but a very good one)
-        // normally we need to checkk all levels, but this is a good heuristic and got all the
-        // optimal values we checked
-        for i in (1..=ecc_u).rev().take(8) {
-            // for i in (1..=ecc_u).rev() {
-            println!("Checking level {}: lb={}, ub={}", i, lb, ub);
-            if ub <= lb {
-                break;
-            }
-
-            let fringe_nodes = &nodes_by_level[i];
-            let max_ecc_in_fringe = fringe_nodes
-                .par_iter()
-                .map(|&node| {
-                    self.bfs(node)
-                        .into_iter()
-                        .filter(|&d| d != usize::MAX)
-                        .max()
-                        .unwrap_or(0)
-                })
-                .max()
-                .unwrap_or(0);
-
-            lb = lb.max(max_ecc_in_fringe);
-
-            if lb > 2 * (i - 1) {
-                return Some(lb);
-            } else {
-                ub = 2 * (i - 1);
-            }
-        }
-
-        Some(lb)
-    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,3 @@
-use std::path::Path;
-
 use rayon::prelude::*;
 
 use geo::Point;
@@ -7,6 +5,7 @@ use geo::{
     polygon, Area, BooleanOps, BoundingRect, Centroid, Contains, Distance, Euclidean, LineString,
 };
 
+use crate::dataset::Config;
 use crate::graph::Graph;
 
 use super::geometric_graph::GeometricGraph;
@@ -126,15 +125,17 @@ pub fn analyze_separators() {
             );
             g.graph.recurse_separator(
                 crate::separator::Mode::Eco,
-                Some(Path::new(&format!("output/nested_grid_level_{}", level))),
+                Some(
+                    &Config::get()
+                        .output("sep")
+                        .join(format!("nested_grid_level_{}", level)),
+                ),
             );
         });
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-
     use super::*;
     use crate::graph::{example::example_c4, Graph};
     use geo::Point;
@@ -158,6 +159,6 @@ mod tests {
     fn test_nested_grid() {
         let g = build_nested_grid(3, 2);
         assert_eq!(g.graph.get_num_nodes(), 5 * 4 + 9);
-        g.save(Path::new("output/nested_grid"));
+        g.save_routingkit(&Config::get().output("graphs").join("nested_grid"));
     }
 }
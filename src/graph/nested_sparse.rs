@@ -167,9 +167,8 @@ pub fn build_sparse_grid(subgraph_counts: &[usize], bottom_grid_width: usize) ->
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-
     use super::*;
+    use crate::dataset::Config;
 
     #[test]
     fn working_base_grid() {
@@ -206,7 +205,7 @@ mod tests {
         let edges = build_sparse_grid(&[0, 5, 50, 1000], 4);
         let g = build_graph_from_edges(&edges);
         g.graph.info();
-        g.save(Path::new("output/sparse_grid"));
+        g.save_routingkit(&Config::get().output("graphs").join("sparse_grid"));
     }
 
     #[test]
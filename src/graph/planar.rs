@@ -1,3 +1,4 @@
+use crate::dataset::Config;
 use crate::library;
 use crate::separator::Mode::*;
 use geo::algorithm::line_intersection::{line_intersection, LineIntersection};
@@ -8,7 +9,6 @@ use rayon::prelude::*;
 use rstar::{PointDistance, RTree, RTreeObject, AABB};
 use std::collections::HashSet;
 use std::mem;
-use std::path::Path;
 
 use super::geometric_graph::GeometricGraph;
 use super::{example, Graph};
@@ -147,11 +147,13 @@ pub fn planarize(g: &mut GeometricGraph) {
 pub fn extend_karlsruhe_separator() {
     let g = example::karlsruhe();
     let sep = g.get_separator_wrapper(Eco);
+    let sep_dir = Config::get().output("sep");
     library::write_text_vec(
         &sep.iter().collect::<Vec<_>>(),
-        Path::new("output/karlsruhe_top_level_sep.txt"),
+        &sep_dir.join("karlsruhe_top_level_sep.txt"),
     );
-    let mut g_planar = Graph::from_file(Path::new("output/karlsruhe_planar")).unwrap();
+    let planar_path = Config::get().output("graphs").join("karlsruhe_planar");
+    let mut g_planar = Graph::from_file(&planar_path).unwrap();
     let subgraphs = g_planar.get_subgraphs(&sep);
     subgraphs
         .iter()
@@ -163,14 +165,15 @@ pub fn extend_karlsruhe_separator() {
     let sep = g_planar.get_separator_wrapper(Strong);
     library::write_text_vec(
         &sep.iter().collect::<Vec<_>>(),
-        Path::new("output/karlsruhe_top_level_sep_planar_additional.txt"),
+        &sep_dir.join("karlsruhe_top_level_sep_planar_additional.txt"),
     );
 }
 
 #[cfg(test)]
 mod test {
-    use std::{fs, path::Path};
+    use std::fs;
 
+    use crate::dataset::Config;
     use crate::graph::{example, Graph};
 
     use super::*;
@@ -198,18 +201,16 @@ mod test {
     fn intersection_karlsruhe() {
         let mut g = example::geometric_germany();
         planarize(&mut g);
-        g.save(Path::new("output/germany_planar"));
+        g.save_routingkit(&Config::get().output("graphs").join("germany_planar"));
         // germany #intersections: 101062
     }
 
     #[test]
     fn load_random_boltzman_planar() {
-        let entries = fs::read_dir(
-            "/home/born/Nextcloud/ws2425/Master/Code/dependencies/BoltzmannPlanarGraphs/out",
-        )
-        .unwrap()
-        .map(|entry| entry.unwrap().path())
-        .collect::<Vec<_>>();
+        let entries = fs::read_dir(&Config::get().boltzmann_planar)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
 
         entries.par_iter().for_each(|entry| {
             let mut g = Graph::from_edge_list_file(entry).unwrap();
@@ -0,0 +1,821 @@
+use geo::algorithm::line_intersection::{line_intersection, LineIntersection};
+use geo::Line;
+use hashbrown::HashMap;
+use rayon::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::{RTree, RTreeObject};
+
+use super::geometric_graph::GeometricGraph;
+use super::Graph;
+
+// combinatorial embedding, the neighbors of every node in clockwise order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embedding {
+    pub rotation: Vec<Vec<usize>>,
+}
+
+pub enum Planarity {
+    Planar(Embedding),
+    // subdivision of K5 or K3,3, on the node ids of the tested graph
+    NonPlanar(Graph),
+}
+
+impl Embedding {
+    // angular order of the drawing, only planar if the drawing has no crossings
+    pub fn from_geometric(g: &GeometricGraph) -> Embedding {
+        let rotation = g
+            .graph
+            .data
+            .par_iter()
+            .enumerate()
+            .map(|(u, neighbors)| {
+                let p = g.get_position(u);
+                let mut neighbors = neighbors.iter().copied().collect::<Vec<_>>();
+                let angle = |v: usize| {
+                    let q = g.get_position(v);
+                    (q.y() - p.y()).atan2(q.x() - p.x())
+                };
+                // decreasing angle is clockwise
+                neighbors.sort_unstable_by(|&a, &b| angle(b).total_cmp(&angle(a)));
+                neighbors
+            })
+            .collect();
+        Embedding { rotation }
+    }
+
+    // every face as the cyclic sequence of nodes on its boundary, the dart u -> v is followed
+    // by v -> w where w comes counterclockwise after u around v
+    pub fn faces(&self) -> Vec<Vec<usize>> {
+        let position = self
+            .rotation
+            .iter()
+            .enumerate()
+            .flat_map(|(u, r)| r.iter().enumerate().map(move |(i, &v)| ((u, v), i)))
+            .collect::<HashMap<_, _>>();
+        let mut visited = self
+            .rotation
+            .iter()
+            .map(|r| vec![false; r.len()])
+            .collect::<Vec<_>>();
+
+        let mut faces = vec![];
+        for u in 0..self.rotation.len() {
+            for i in 0..self.rotation[u].len() {
+                if visited[u][i] {
+                    continue;
+                }
+                let mut face = vec![];
+                let (mut a, mut j) = (u, i);
+                while !visited[a][j] {
+                    visited[a][j] = true;
+                    face.push(a);
+                    let b = self.rotation[a][j];
+                    let k = position[&(b, a)];
+                    let len = self.rotation[b].len();
+                    (a, j) = (b, (k + len - 1) % len);
+                }
+                faces.push(face);
+            }
+        }
+        faces
+    }
+
+    // 0 iff the rotation system is a planar embedding, by euler's formula per component
+    pub fn genus(&self) -> usize {
+        let n = self.rotation.len();
+        let darts = self.rotation.iter().map(|r| r.len()).sum::<usize>();
+        let faces = self.faces().len();
+
+        let mut component = vec![usize::MAX; n];
+        let mut components = 0;
+        let mut isolated = 0;
+        for s in 0..n {
+            if component[s] != usize::MAX {
+                continue;
+            }
+            if self.rotation[s].is_empty() {
+                isolated += 1;
+            }
+            component[s] = components;
+            let mut stack = vec![s];
+            while let Some(u) = stack.pop() {
+                for &v in &self.rotation[u] {
+                    if component[v] == usize::MAX {
+                        component[v] = components;
+                        stack.push(v);
+                    }
+                }
+            }
+            components += 1;
+        }
+        // n - m + f = 2c - 2g, an isolated node has one face that is not traversed
+        (2 * components + darts / 2 - (n + faces + isolated)) / 2
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Interval {
+    low: Option<usize>,
+    high: Option<usize>,
+}
+
+impl Interval {
+    fn is_empty(&self) -> bool {
+        self.low.is_none() && self.high.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ConflictPair {
+    left: Interval,
+    right: Interval,
+}
+
+impl ConflictPair {
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.left, &mut self.right);
+    }
+}
+
+// doubly linked rotation around every node, as the embedding phase inserts relative to
+// neighbors that are already placed
+struct RotationBuilder {
+    cw: HashMap<(usize, usize), usize>,
+    ccw: HashMap<(usize, usize), usize>,
+    first: Vec<Option<usize>>,
+}
+
+impl RotationBuilder {
+    fn add_cw(&mut self, start: usize, end: usize, reference: Option<usize>) {
+        match reference {
+            None => {
+                self.cw.insert((start, end), end);
+                self.ccw.insert((start, end), end);
+                self.first[start] = Some(end);
+            }
+            Some(r) => {
+                let r_cw = self.cw[&(start, r)];
+                self.cw.insert((start, r), end);
+                self.ccw.insert((start, end), r);
+                self.cw.insert((start, end), r_cw);
+                self.ccw.insert((start, r_cw), end);
+            }
+        }
+    }
+
+    fn add_ccw(&mut self, start: usize, end: usize, reference: Option<usize>) {
+        match reference {
+            None => self.add_cw(start, end, None),
+            Some(r) => {
+                let r_ccw = self.ccw[&(start, r)];
+                self.add_cw(start, end, Some(r_ccw));
+                if self.first[start] == Some(r) {
+                    self.first[start] = Some(end);
+                }
+            }
+        }
+    }
+
+    fn add_first(&mut self, start: usize, end: usize) {
+        self.add_ccw(start, end, self.first[start]);
+    }
+
+    fn into_embedding(self) -> Embedding {
+        let rotation = self
+            .first
+            .iter()
+            .enumerate()
+            .map(|(u, first)| {
+                let mut r = vec![];
+                if let Some(first) = *first {
+                    let mut v = first;
+                    loop {
+                        r.push(v);
+                        v = self.cw[&(u, v)];
+                        if v == first {
+                            break;
+                        }
+                    }
+                }
+                r
+            })
+            .collect();
+        Embedding { rotation }
+    }
+}
+
+// left-right planarity test (Brandes, "The Left-Right Planarity Test", 2009), all three dfs
+// phases are iterative as road networks are far too deep for recursion
+struct LeftRight<'a> {
+    graph: &'a Graph,
+    height: Vec<usize>,
+    parent_edge: Vec<Option<usize>>,
+    roots: Vec<usize>,
+    // oriented edges, out[u] is sorted by nesting depth once the orientation is known
+    src: Vec<usize>,
+    dst: Vec<usize>,
+    edge_id: HashMap<(usize, usize), usize>,
+    out: Vec<Vec<usize>>,
+    lowpt: Vec<usize>,
+    lowpt2: Vec<usize>,
+    nesting_depth: Vec<i64>,
+    refs: Vec<Option<usize>>,
+    side: Vec<i64>,
+    lowpt_edge: Vec<Option<usize>>,
+    // stack height when the edge was entered
+    stack_bottom: Vec<usize>,
+    stack: Vec<ConflictPair>,
+}
+
+impl<'a> LeftRight<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        let n = graph.get_num_nodes();
+        LeftRight {
+            graph,
+            height: vec![usize::MAX; n],
+            parent_edge: vec![None; n],
+            roots: vec![],
+            src: vec![],
+            dst: vec![],
+            edge_id: HashMap::new(),
+            out: vec![vec![]; n],
+            lowpt: vec![],
+            lowpt2: vec![],
+            nesting_depth: vec![],
+            refs: vec![],
+            side: vec![],
+            lowpt_edge: vec![],
+            stack_bottom: vec![],
+            stack: vec![],
+        }
+    }
+
+    fn run(mut self) -> Option<Embedding> {
+        let n = self.graph.get_num_nodes();
+        if n > 2 && self.graph.get_num_edges() > 3 * n - 6 {
+            return None;
+        }
+
+        let adjs = self
+            .graph
+            .data
+            .iter()
+            .enumerate()
+            .map(|(u, neighbors)| {
+                let mut a = neighbors
+                    .iter()
+                    .copied()
+                    .filter(|&v| v != u)
+                    .collect::<Vec<_>>();
+                a.sort_unstable();
+                a
+            })
+            .collect::<Vec<_>>();
+        for root in 0..n {
+            if self.height[root] == usize::MAX {
+                self.height[root] = 0;
+                self.roots.push(root);
+                self.orientation(root, &adjs);
+            }
+        }
+
+        let m = self.src.len();
+        self.refs = vec![None; m];
+        self.side = vec![1; m];
+        self.lowpt_edge = vec![None; m];
+        self.stack_bottom = vec![0; m];
+        self.sort_out_edges();
+        for root in self.roots.clone() {
+            if !self.testing(root) {
+                return None;
+            }
+        }
+
+        for e in 0..m {
+            self.nesting_depth[e] *= self.sign(e);
+        }
+        self.sort_out_edges();
+        let mut builder = RotationBuilder {
+            cw: HashMap::new(),
+            ccw: HashMap::new(),
+            first: vec![None; n],
+        };
+        for v in 0..n {
+            let mut previous = None;
+            for &e in &self.out[v] {
+                builder.add_cw(v, self.dst[e], previous);
+                previous = Some(self.dst[e]);
+            }
+        }
+        for root in self.roots.clone() {
+            self.embedding(root, &mut builder);
+        }
+        Some(builder.into_embedding())
+    }
+
+    fn sort_out_edges(&mut self) {
+        for out in self.out.iter_mut() {
+            out.sort_by_key(|&e| self.nesting_depth[e]);
+        }
+    }
+
+    fn orientation(&mut self, root: usize, adjs: &[Vec<usize>]) {
+        let mut stack = vec![root];
+        let mut index = vec![0; adjs.len()];
+        let mut skip_init = HashMap::new();
+        while let Some(v) = stack.pop() {
+            let e = self.parent_edge[v];
+            while index[v] < adjs[v].len() {
+                let w = adjs[v][index[v]];
+                let vw = match self.edge_id.get(&(v, w)) {
+                    Some(&vw) if skip_init.contains_key(&vw) => vw,
+                    Some(_) => {
+                        index[v] += 1;
+                        continue;
+                    }
+                    None if self.edge_id.contains_key(&(w, v)) => {
+                        index[v] += 1;
+                        continue;
+                    }
+                    None => {
+                        let vw = self.src.len();
+                        self.src.push(v);
+                        self.dst.push(w);
+                        self.edge_id.insert((v, w), vw);
+                        self.out[v].push(vw);
+                        self.lowpt.push(self.height[v]);
+                        self.lowpt2.push(self.height[v]);
+                        self.nesting_depth.push(0);
+                        if self.height[w] == usize::MAX {
+                            // tree edge, come back to v once w is finished
+                            self.parent_edge[w] = Some(vw);
+                            self.height[w] = self.height[v] + 1;
+                            skip_init.insert(vw, ());
+                            stack.push(v);
+                            stack.push(w);
+                            break;
+                        }
+                        // back edge
+                        self.lowpt[vw] = self.height[w];
+                        vw
+                    }
+                };
+
+                self.nesting_depth[vw] = 2 * self.lowpt[vw] as i64;
+                if self.lowpt2[vw] < self.height[v] {
+                    // chordal
+                    self.nesting_depth[vw] += 1;
+                }
+                if let Some(e) = e {
+                    if self.lowpt[vw] < self.lowpt[e] {
+                        self.lowpt2[e] = self.lowpt[e].min(self.lowpt2[vw]);
+                        self.lowpt[e] = self.lowpt[vw];
+                    } else if self.lowpt[vw] > self.lowpt[e] {
+                        self.lowpt2[e] = self.lowpt2[e].min(self.lowpt[vw]);
+                    } else {
+                        self.lowpt2[e] = self.lowpt2[e].min(self.lowpt2[vw]);
+                    }
+                }
+                index[v] += 1;
+            }
+        }
+    }
+
+    fn conflicting(&self, interval: Interval, b: usize) -> bool {
+        interval
+            .high
+            .is_some_and(|high| self.lowpt[high] > self.lowpt[b])
+    }
+
+    fn lowest(&self, p: &ConflictPair) -> usize {
+        match (p.left.low, p.right.low) {
+            (None, Some(r)) => self.lowpt[r],
+            (Some(l), None) => self.lowpt[l],
+            (Some(l), Some(r)) => self.lowpt[l].min(self.lowpt[r]),
+            (None, None) => unreachable!("empty conflict pair on the stack"),
+        }
+    }
+
+    fn testing(&mut self, root: usize) -> bool {
+        let mut stack = vec![root];
+        let mut index = vec![0; self.out.len()];
+        let mut skip_init = vec![false; self.src.len()];
+        while let Some(v) = stack.pop() {
+            let e = self.parent_edge[v];
+            let mut skip_final = false;
+            while index[v] < self.out[v].len() {
+                let ei = self.out[v][index[v]];
+                let w = self.dst[ei];
+                if !skip_init[ei] {
+                    self.stack_bottom[ei] = self.stack.len();
+                    if self.parent_edge[w] == Some(ei) {
+                        stack.push(v);
+                        stack.push(w);
+                        skip_init[ei] = true;
+                        skip_final = true;
+                        break;
+                    }
+                    self.lowpt_edge[ei] = Some(ei);
+                    self.stack.push(ConflictPair {
+                        left: Interval::default(),
+                        right: Interval {
+                            low: Some(ei),
+                            high: Some(ei),
+                        },
+                    });
+                }
+
+                // integrate the return edges of ei
+                if self.lowpt[ei] < self.height[v] {
+                    let e = e.unwrap();
+                    if index[v] == 0 {
+                        self.lowpt_edge[e] = self.lowpt_edge[ei];
+                    } else if !self.add_constraints(ei, e) {
+                        return false;
+                    }
+                }
+                index[v] += 1;
+            }
+            if !skip_final {
+                if let Some(e) = e {
+                    self.remove_back_edges(e);
+                }
+            }
+        }
+        true
+    }
+
+    fn add_constraints(&mut self, ei: usize, e: usize) -> bool {
+        let mut p = ConflictPair::default();
+        // merge the return edges of ei into p.right
+        loop {
+            let mut q = self.stack.pop().unwrap();
+            if !q.left.is_empty() {
+                q.swap();
+            }
+            if !q.left.is_empty() {
+                return false;
+            }
+            if self.lowpt[q.right.low.unwrap()] > self.lowpt[e] {
+                if p.right.is_empty() {
+                    p.right = q.right;
+                } else {
+                    self.refs[p.right.low.unwrap()] = q.right.high;
+                }
+                p.right.low = q.right.low;
+            } else {
+                self.refs[q.right.low.unwrap()] = self.lowpt_edge[e];
+            }
+            if self.stack.len() == self.stack_bottom[ei] {
+                break;
+            }
+        }
+
+        // merge the conflicting return edges of the earlier siblings into p.left
+        while let Some(&top) = self.stack.last() {
+            if !self.conflicting(top.left, ei) && !self.conflicting(top.right, ei) {
+                break;
+            }
+            let mut q = self.stack.pop().unwrap();
+            if self.conflicting(q.right, ei) {
+                q.swap();
+            }
+            if self.conflicting(q.right, ei) {
+                return false;
+            }
+            if let Some(low) = p.right.low {
+                self.refs[low] = q.right.high;
+            }
+            if q.right.low.is_some() {
+                p.right.low = q.right.low;
+            }
+            if p.left.is_empty() {
+                p.left = q.left;
+            } else {
+                self.refs[p.left.low.unwrap()] = q.left.high;
+            }
+            p.left.low = q.left.low;
+        }
+
+        if !p.left.is_empty() || !p.right.is_empty() {
+            self.stack.push(p);
+        }
+        true
+    }
+
+    fn remove_back_edges(&mut self, e: usize) {
+        let u = self.src[e];
+        // drop the conflict pairs whose return edges all end at u
+        while let Some(top) = self.stack.last() {
+            if self.lowest(top) != self.height[u] {
+                break;
+            }
+            let p = self.stack.pop().unwrap();
+            if let Some(low) = p.left.low {
+                self.side[low] = -1;
+            }
+        }
+
+        // and trim the next one
+        if let Some(mut p) = self.stack.pop() {
+            while let Some(high) = p.left.high.filter(|&h| self.dst[h] == u) {
+                p.left.high = self.refs[high];
+            }
+            if p.left.high.is_none() {
+                if let Some(low) = p.left.low.take() {
+                    self.refs[low] = p.right.low;
+                    self.side[low] = -1;
+                }
+            }
+            while let Some(high) = p.right.high.filter(|&h| self.dst[h] == u) {
+                p.right.high = self.refs[high];
+            }
+            if p.right.high.is_none() {
+                if let Some(low) = p.right.low.take() {
+                    self.refs[low] = p.left.low;
+                    self.side[low] = -1;
+                }
+            }
+            self.stack.push(p);
+        }
+
+        // e lies on the side of a highest return edge
+        if self.lowpt[e] < self.height[u] {
+            let top = self.stack.last().unwrap();
+            let (hl, hr) = (top.left.high, top.right.high);
+            self.refs[e] = match (hl, hr) {
+                (Some(l), None) => Some(l),
+                (Some(l), Some(r)) if self.lowpt[l] > self.lowpt[r] => Some(l),
+                _ => hr,
+            };
+        }
+    }
+
+    // resolves the side of e relative to its reference chain
+    fn sign(&mut self, e: usize) -> i64 {
+        let mut stack = vec![e];
+        let mut old_refs = HashMap::new();
+        while let Some(f) = stack.pop() {
+            if let Some(r) = self.refs[f].take() {
+                stack.push(f);
+                stack.push(r);
+                old_refs.insert(f, r);
+            } else if let Some(&r) = old_refs.get(&f) {
+                self.side[f] *= self.side[r];
+            }
+        }
+        self.side[e]
+    }
+
+    fn embedding(&mut self, root: usize, builder: &mut RotationBuilder) {
+        let n = self.out.len();
+        let mut left_ref = vec![0; n];
+        let mut right_ref = vec![0; n];
+        let mut index = vec![0; n];
+        let mut stack = vec![root];
+        while let Some(v) = stack.pop() {
+            while index[v] < self.out[v].len() {
+                let ei = self.out[v][index[v]];
+                index[v] += 1;
+                let w = self.dst[ei];
+                if self.parent_edge[w] == Some(ei) {
+                    builder.add_first(w, v);
+                    left_ref[v] = w;
+                    right_ref[v] = w;
+                    stack.push(v);
+                    stack.push(w);
+                    break;
+                }
+                if self.side[ei] == 1 {
+                    builder.add_cw(w, v, Some(right_ref[w]));
+                } else {
+                    builder.add_ccw(w, v, Some(left_ref[w]));
+                    left_ref[w] = v;
+                }
+            }
+        }
+    }
+}
+
+impl Graph {
+    pub fn planar_embedding(&self) -> Option<Embedding> {
+        LeftRight::new(self).run()
+    }
+
+    pub fn is_planar(&self) -> bool {
+        self.planar_embedding().is_some()
+    }
+
+    pub fn planarity(&self) -> Planarity {
+        match self.planar_embedding() {
+            Some(embedding) => Planarity::Planar(embedding),
+            None => Planarity::NonPlanar(self.kuratowski_subgraph()),
+        }
+    }
+
+    // deletes edges as long as the rest stays non-planar, what is left is a minimal non-planar
+    // subgraph. edges are tried in growing blocks, so only about k log m tests are needed
+    pub fn kuratowski_subgraph(&self) -> Graph {
+        let n = self.get_num_nodes();
+        let edges = self
+            .get_edges()
+            .into_iter()
+            .filter(|&(u, v)| u < v)
+            .collect::<Vec<_>>();
+        let subgraph = |edges: &mut dyn Iterator<Item = &(usize, usize)>| {
+            let mut g = Graph::with_node_count(n);
+            for &(u, v) in edges {
+                g.add_edge(u, v);
+            }
+            g
+        };
+        assert!(
+            !self.is_planar(),
+            "planar graphs have no kuratowski subgraph"
+        );
+
+        let mut kept = vec![];
+        let mut i = 0;
+        let mut block = 1;
+        while i < edges.len() {
+            let b = block.min(edges.len() - i);
+            let rest = subgraph(&mut kept.iter().chain(&edges[i + b..]));
+            if !rest.is_planar() {
+                i += b;
+                block *= 2;
+            } else if b > 1 {
+                block = b / 2;
+            } else {
+                kept.push(edges[i]);
+                i += 1;
+            }
+        }
+        subgraph(&mut kept.iter())
+    }
+}
+
+impl GeometricGraph {
+    // pairs of edges that cross in the straight line drawing, touching at a shared endpoint or
+    // a node lying on an edge does not count
+    pub fn count_crossings(&self) -> usize {
+        let lines = self
+            .graph
+            .get_edges()
+            .into_iter()
+            .filter(|&(u, v)| u < v)
+            .map(|(u, v)| {
+                GeomWithData::new(
+                    Line::new(self.get_position(u), self.get_position(v)),
+                    (u, v),
+                )
+            })
+            .collect::<Vec<_>>();
+        let tree = RTree::bulk_load(lines.clone());
+
+        lines
+            .par_iter()
+            .map(|a| {
+                tree.locate_in_envelope_intersecting(&a.envelope())
+                    .filter(|b| a.data < b.data)
+                    .filter(|b| {
+                        let (a, b) = (a.data, b.data);
+                        a.0 != b.0 && a.0 != b.1 && a.1 != b.0 && a.1 != b.1
+                    })
+                    .filter(|b| {
+                        matches!(
+                            line_intersection(*a.geom(), *b.geom()),
+                            Some(LineIntersection::SinglePoint {
+                                is_proper: true,
+                                ..
+                            }) | Some(LineIntersection::Collinear { .. })
+                        )
+                    })
+                    .count()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::planar::planarize;
+    use crate::graph::{delaunay, gabriel_graph, grid, relative_neighborhood};
+
+    fn complete(n: usize) -> Graph {
+        let mut g = Graph::with_node_count(n);
+        for u in 0..n {
+            for v in u + 1..n {
+                g.add_edge(u, v);
+            }
+        }
+        g
+    }
+
+    fn complete_bipartite(a: usize, b: usize) -> Graph {
+        let mut g = Graph::with_node_count(a + b);
+        for u in 0..a {
+            for v in a..a + b {
+                g.add_edge(u, v);
+            }
+        }
+        g
+    }
+
+    #[test]
+    fn kuratowski_graphs() {
+        assert!(complete(4).is_planar());
+        assert!(!complete(5).is_planar());
+        assert!(!complete_bipartite(3, 3).is_planar());
+        assert!(complete_bipartite(2, 10).is_planar());
+
+        let mut k5 = complete(5);
+        k5.remove_edge(0, 1);
+        assert!(k5.is_planar());
+        let mut k33 = complete_bipartite(3, 3);
+        k33.remove_edge(0, 3);
+        assert!(k33.is_planar());
+    }
+
+    #[test]
+    fn embeddings_are_planar() {
+        for g in [
+            grid::generate_grid(20),
+            complete(4),
+            complete_bipartite(2, 5),
+        ] {
+            let embedding = g.planar_embedding().unwrap();
+            assert_eq!(embedding.genus(), 0);
+            for u in g.nodes_iter() {
+                assert_eq!(embedding.rotation[u].len(), g.degree(u));
+            }
+        }
+        // 19 * 19 squares and the outer face
+        let faces = grid::generate_grid(20).planar_embedding().unwrap().faces();
+        assert_eq!(faces.len(), 19 * 19 + 1);
+
+        let mut disconnected = grid::generate_grid(3);
+        disconnected.increase_size_to(12);
+        disconnected.add_edge(10, 11);
+        assert_eq!(disconnected.planar_embedding().unwrap().genus(), 0);
+    }
+
+    #[test]
+    fn generators_are_planar() {
+        for g in [
+            delaunay::delaunay(2_000),
+            gabriel_graph::gabriel_graph(2_000),
+            relative_neighborhood::relative_neighborhood(2_000),
+        ] {
+            assert!(g.graph.is_planar());
+            assert_eq!(g.count_crossings(), 0);
+            assert_eq!(Embedding::from_geometric(&g).genus(), 0);
+        }
+    }
+
+    #[test]
+    fn extract_kuratowski() {
+        // K3,3 hidden in a planar grid by one extra chord
+        let mut g = complete_bipartite(3, 3);
+        g.increase_size_to(30);
+        for u in 6..29 {
+            g.add_edge(u, u + 1);
+        }
+        g.add_edge(0, 6);
+        match g.planarity() {
+            Planarity::Planar(_) => panic!("contains K3,3"),
+            Planarity::NonPlanar(k) => {
+                assert_eq!(k.get_num_edges(), 9);
+                assert!(!k.is_planar());
+            }
+        }
+
+        let k = complete(7).kuratowski_subgraph();
+        assert!(!k.is_planar());
+        for (u, v) in k.get_edges().into_iter().filter(|&(u, v)| u < v) {
+            let mut smaller = k.clone();
+            smaller.remove_edge(u, v);
+            assert!(smaller.is_planar());
+        }
+    }
+
+    #[test]
+    fn crossings() {
+        let mut g = GeometricGraph::new(
+            complete(4),
+            vec![
+                geo::Point::new(0.0, 0.0),
+                geo::Point::new(1.0, 0.0),
+                geo::Point::new(0.0, 1.0),
+                geo::Point::new(1.0, 1.0),
+            ],
+        );
+        // the diagonals 0-3 and 1-2 cross
+        assert_eq!(g.count_crossings(), 1);
+        assert!(Embedding::from_geometric(&g).genus() > 0);
+        assert!(g.graph.is_planar());
+
+        planarize(&mut g);
+        assert_eq!(g.count_crossings(), 0);
+    }
+}
@@ -0,0 +1,218 @@
+use geo::{Distance, Euclidean, Geodesic, Haversine, Point};
+
+// WGS84 ellipsoid
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+// scale factor and false easting as used by UTM
+const UTM_SCALE: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+const UTM_ZONE_WIDTH: f64 = 6.0;
+
+// how the positions of a graph are to be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crs {
+    // x = longitude, y = latitude in degrees
+    Wgs84,
+    // cartesian coordinates, meters for projected road networks
+    Planar,
+}
+
+impl Crs {
+    pub fn distance(&self, a: Point, b: Point) -> f64 {
+        match self {
+            Crs::Wgs84 => Haversine::distance(a, b),
+            Crs::Planar => Euclidean::distance(a, b),
+        }
+    }
+
+    // ellipsoidal distance, slower but exact up to float precision
+    pub fn geodesic_distance(&self, a: Point, b: Point) -> f64 {
+        match self {
+            Crs::Wgs84 => Geodesic::distance(a, b),
+            Crs::Planar => Euclidean::distance(a, b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // spherical mercator in meters, lengths are scaled by 1 / cos(latitude)
+    WebMercator,
+    // ellipsoidal transverse mercator around the given meridian, close to true lengths within ~3° of it
+    TransverseMercator { central_meridian: f64 },
+    // ellipsoidal lambert azimuthal equal-area around the given point (x = longitude), keeps areas
+    // for continent-scale data, lengths are off by a few percent 2000km from the center
+    LambertAzimuthalEqualArea { center: Point },
+}
+
+impl Projection {
+    pub fn utm_zone(longitude: f64) -> u8 {
+        (((longitude + 180.0) / UTM_ZONE_WIDTH).floor() as i64).clamp(0, 59) as u8 + 1
+    }
+
+    pub fn utm(zone: u8) -> Projection {
+        Projection::TransverseMercator {
+            central_meridian: zone as f64 * UTM_ZONE_WIDTH - 183.0,
+        }
+    }
+
+    // transverse mercator in the utm zone of the mean longitude of the given wgs84 points,
+    // equal-area around the mean point if they span more than one zone
+    pub fn local(points: &[Point]) -> Projection {
+        let count = points.len().max(1) as f64;
+        let mean_lon = points.iter().map(|p| p.x()).sum::<f64>() / count;
+        let (min_lon, max_lon) = points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+                (lo.min(p.x()), hi.max(p.x()))
+            });
+        if max_lon - min_lon <= UTM_ZONE_WIDTH {
+            return Projection::utm(Projection::utm_zone(mean_lon));
+        }
+        let mean_lat = points.iter().map(|p| p.y()).sum::<f64>() / count;
+        Projection::LambertAzimuthalEqualArea {
+            center: Point::new(mean_lon, mean_lat),
+        }
+    }
+
+    pub fn project(&self, p: Point) -> Point {
+        match *self {
+            Projection::WebMercator => web_mercator(p),
+            Projection::TransverseMercator { central_meridian } => {
+                transverse_mercator(p, central_meridian)
+            }
+            Projection::LambertAzimuthalEqualArea { center } => {
+                lambert_azimuthal_equal_area(p, center)
+            }
+        }
+    }
+}
+
+fn web_mercator(p: Point) -> Point {
+    let lat = p.y().clamp(-85.051_129, 85.051_129).to_radians();
+    Point::new(
+        SEMI_MAJOR_AXIS * p.x().to_radians(),
+        SEMI_MAJOR_AXIS * (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln(),
+    )
+}
+
+// series expansion from Snyder, "Map Projections: A Working Manual", p. 61
+fn transverse_mercator(p: Point, central_meridian: f64) -> Point {
+    let e2 = FLATTENING * (2.0 - FLATTENING);
+    let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+    let ep2 = e2 / (1.0 - e2);
+
+    let phi = p.y().to_radians();
+    let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+    let n = SEMI_MAJOR_AXIS / (1.0 - e2 * sin * sin).sqrt();
+    let t = tan * tan;
+    let c = ep2 * cos * cos;
+    let a = cos * (p.x() - central_meridian).to_radians();
+
+    let m = SEMI_MAJOR_AXIS
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin());
+
+    let x = UTM_SCALE
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0);
+    let y = UTM_SCALE
+        * (m + n
+            * tan
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+
+    Point::new(x + UTM_FALSE_EASTING, y)
+}
+
+// q of the authalic latitude, Snyder p. 187
+fn authalic_q(sin_phi: f64, e: f64) -> f64 {
+    let e2 = e * e;
+    (1.0 - e2)
+        * (sin_phi / (1.0 - e2 * sin_phi * sin_phi)
+            - ((1.0 - e * sin_phi) / (1.0 + e * sin_phi)).ln() / (2.0 * e))
+}
+
+// oblique aspect from Snyder, "Map Projections: A Working Manual", p. 187
+fn lambert_azimuthal_equal_area(p: Point, center: Point) -> Point {
+    let e = (FLATTENING * (2.0 - FLATTENING)).sqrt();
+    let qp = authalic_q(1.0, e);
+    let rq = SEMI_MAJOR_AXIS * (qp / 2.0).sqrt();
+    let beta = |phi: f64| (authalic_q(phi.sin(), e) / qp).asin();
+
+    let phi1 = center.y().to_radians();
+    let beta1 = beta(phi1);
+    let m1 = phi1.cos() / (1.0 - e * e * phi1.sin().powi(2)).sqrt();
+    let d = SEMI_MAJOR_AXIS * m1 / (rq * beta1.cos());
+
+    let beta = beta(p.y().to_radians());
+    let lambda = (p.x() - center.x()).to_radians();
+    let b = rq
+        * (2.0 / (1.0 + beta1.sin() * beta.sin() + beta1.cos() * beta.cos() * lambda.cos())).sqrt();
+
+    Point::new(
+        b * d * beta.cos() * lambda.sin(),
+        b / d * (beta1.cos() * beta.sin() - beta1.sin() * beta.cos() * lambda.cos()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn utm_karlsruhe() {
+        let p = Point::new(8.4044, 49.0135);
+        assert_eq!(Projection::utm_zone(p.x()), 32);
+        let projection = Projection::utm(32);
+
+        // on the central meridian the northing is the scaled meridian arc
+        let meridian = Point::new(9.0, p.y());
+        let arc = Crs::Wgs84.geodesic_distance(Point::new(9.0, 0.0), meridian);
+        let q = projection.project(meridian);
+        assert!((q.x() - 500_000.0).abs() < 1e-6);
+        assert!((q.y() - UTM_SCALE * arc).abs() < 0.01);
+
+        let q = projection.project(p);
+        let offset = Crs::Wgs84.geodesic_distance(p, meridian);
+        assert!((500_000.0 - q.x() - UTM_SCALE * offset).abs() < 1.0);
+    }
+
+    #[test]
+    fn projected_lengths() {
+        let a = Point::new(8.40, 49.00);
+        let b = Point::new(8.45, 49.03);
+        let geodesic = Crs::Wgs84.geodesic_distance(a, b);
+        assert!((Crs::Wgs84.distance(a, b) - geodesic).abs() / geodesic < 5e-3);
+
+        let projection = Projection::local(&[a, b]);
+        let projected = Crs::Planar.distance(projection.project(a), projection.project(b));
+        assert!((projected - geodesic).abs() / geodesic < 1e-3);
+
+        // a continent does not fit into one utm zone
+        let europe = [Point::new(-9.0, 38.7), Point::new(30.3, 59.9)];
+        assert!(matches!(
+            Projection::local(&europe),
+            Projection::LambertAzimuthalEqualArea { .. }
+        ));
+
+        // one degree of longitude on the equator
+        let x = Projection::WebMercator.project(Point::new(1.0, 0.0)).x();
+        assert!((x - 111_319.49).abs() < 0.01);
+    }
+
+    #[test]
+    fn etrs_laea() {
+        // EPSG:3035 without false easting and northing, example from EPSG guidance note 7-2
+        let projection = Projection::LambertAzimuthalEqualArea {
+            center: Point::new(10.0, 52.0),
+        };
+        let q = projection.project(Point::new(5.0, 50.0));
+        assert!((q.x() - (3_962_799.45 - 4_321_000.0)).abs() < 0.01);
+        assert!((q.y() - (2_999_718.85 - 3_210_000.0)).abs() < 0.01);
+    }
+}
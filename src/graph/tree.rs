@@ -28,11 +28,11 @@ pub fn random_tree(n: usize) -> Graph {
 mod tests {
     use std::fs::File;
     use std::io::{self, Write};
-    use std::path::Path;
 
     use rayon::iter::IntoParallelIterator;
 
     use super::*;
+    use crate::dataset::Config;
 
     #[test]
     fn test_generate_random_tree() {
@@ -61,10 +61,14 @@ mod tests {
             }
         }
 
-        File::create("output/diameter_overview.txt")
-            .unwrap()
-            .write_all(result.as_bytes())
-            .unwrap();
+        File::create(
+            Config::get()
+                .output("diameter")
+                .join("diameter_overview.txt"),
+        )
+        .unwrap()
+        .write_all(result.as_bytes())
+        .unwrap();
     }
 
     #[test]
@@ -75,7 +79,11 @@ mod tests {
             let g = crate::local::no_locality(n, n * 5 / 4);
             g.recurse_separator(
                 crate::separator::Mode::Eco,
-                Some(Path::new(&format!("./output/sep/RandomAvgDeg_{}", n))),
+                Some(
+                    &Config::get()
+                        .output("sep")
+                        .join(format!("RandomAvgDeg_{}", n)),
+                ),
             );
         });
     }
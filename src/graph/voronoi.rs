@@ -306,6 +306,7 @@ pub fn prune_v3(geo_graph: &mut GeometricGraph, t: f64) {
             data: vec![HashSet::new(); num_nodes],
         },
         positions: geo_graph.positions.clone(),
+        crs: geo_graph.crs,
     };
     let mut uf = UnionFind::new(num_nodes);
 
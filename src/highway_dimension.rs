@@ -0,0 +1,295 @@
+use chrono::Local;
+use geo::Point;
+use hashbrown::{DefaultHashBuilder, HashMap};
+use ordered_float::OrderedFloat;
+use priority_queue::PriorityQueue;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use std::cmp::Reverse;
+use std::fs;
+
+use crate::dataset::Config;
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::{delaunay, example, gabriel_graph, noise, relative_neighborhood};
+
+#[derive(Debug, Clone)]
+pub struct HighwayDimension {
+    pub radius: f64,
+    // greedy hitting set size per sampled ball
+    pub hitting_sets: Vec<usize>,
+    pub max: usize,
+    pub mean: f64,
+}
+
+// settled nodes in order of distance with their distance and tree parent
+struct ShortestPathTree {
+    order: Vec<usize>,
+    distance: HashMap<usize, f64>,
+    parent: HashMap<usize, usize>,
+}
+
+impl GeometricGraph {
+    // dijkstra from s that stops at distance bound, only touches the ball around s
+    fn shortest_path_tree(&self, s: usize, bound: f64) -> ShortestPathTree {
+        let mut tree = ShortestPathTree {
+            order: vec![],
+            distance: HashMap::new(),
+            parent: HashMap::new(),
+        };
+        let mut tentative = HashMap::new();
+        tentative.insert(s, 0.0);
+        let mut pq = PriorityQueue::<_, _, DefaultHashBuilder>::with_default_hasher();
+        pq.push(s, Reverse(OrderedFloat(0.0)));
+
+        while let Some((u, Reverse(OrderedFloat(u_dist)))) = pq.pop() {
+            if u_dist > bound {
+                break;
+            }
+            tree.order.push(u);
+            tree.distance.insert(u, u_dist);
+            for &v in self.graph.get_neighbors(u) {
+                if tree.distance.contains_key(&v) {
+                    continue;
+                }
+                let v_dist = u_dist + self.euclidean_distance(u, v);
+                if tentative.get(&v).is_none_or(|&d| v_dist < d) {
+                    tentative.insert(v, v_dist);
+                    tree.parent.insert(v, u);
+                    pq.push_increase(v, Reverse(OrderedFloat(v_dist)));
+                }
+            }
+        }
+        tree
+    }
+
+    // paths of length in (r, 2r] that pass through the ball of radius r around c. only the
+    // shortest prefixes longer than r are kept, a node on them hits every longer path as well
+    fn crossing_paths(&self, c: usize, r: f64, sources: usize) -> Vec<Vec<usize>> {
+        let ball = self.shortest_path_tree(c, r).distance;
+        // a crossing path of length at most 2r starts within 3r of c
+        let mut candidates = self.shortest_path_tree(c, 3.0 * r).order;
+        candidates.shuffle(&mut thread_rng());
+        candidates.truncate(sources);
+
+        let mut paths = vec![];
+        for s in candidates {
+            let tree = self.shortest_path_tree(s, 2.0 * r);
+            for &t in &tree.order {
+                if tree.distance[&t] <= r || tree.distance[&tree.parent[&t]] > r {
+                    continue;
+                }
+                let mut path = vec![t];
+                while let Some(&p) = tree.parent.get(path.last().unwrap()) {
+                    path.push(p);
+                }
+                if path.iter().any(|u| ball.contains_key(u)) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    // highway dimension estimate at radius r (Abraham et al. 2010): the largest greedy hitting
+    // set of the crossing paths over the sampled balls. paths are sampled from at most sources
+    // start nodes per ball, so this is a lower estimate of the greedy value
+    pub fn highway_dimension(&self, r: f64, samples: usize, sources: usize) -> HighwayDimension {
+        let n = self.graph.get_num_nodes();
+        let samples = if n == 0 { 0 } else { samples };
+        let hitting_sets = (0..samples)
+            .into_par_iter()
+            .map(|_| {
+                let c = thread_rng().gen_range(0..n);
+                greedy_hitting_set(&self.crossing_paths(c, r, sources)).len()
+            })
+            .collect::<Vec<_>>();
+        HighwayDimension {
+            radius: r,
+            max: hitting_sets.iter().copied().max().unwrap_or(0),
+            mean: hitting_sets.iter().sum::<usize>() as f64 / samples.max(1) as f64,
+            hitting_sets,
+        }
+    }
+
+    // skeleton width of the shortest path tree from s (Kosowski and Viennot 2017). a point x of
+    // the tree belongs to the skeleton if some descendant is at least d(s, x) / 2 further away,
+    // the width is the largest number of skeleton points at the same distance from s
+    pub fn skeleton_width(&self, s: usize) -> usize {
+        let tree = self.shortest_path_tree(s, f64::INFINITY);
+        let mut height = HashMap::new();
+        for &v in tree.order.iter().rev() {
+            let h = *height.entry(v).or_insert(0.0);
+            if let Some(&u) = tree.parent.get(&v) {
+                let up = h + tree.distance[&v] - tree.distance[&u];
+                let hu = height.entry(u).or_insert(0.0);
+                *hu = f64::max(*hu, up);
+            }
+        }
+
+        // the point at distance x on edge (u, v) is in the skeleton while
+        // height(v) + d(v) - x >= x / 2, each edge covers an interval of distances
+        let mut events = vec![];
+        for (&v, &u) in &tree.parent {
+            let start = tree.distance[&u];
+            let end = f64::min(
+                tree.distance[&v],
+                2.0 * (height[&v] + tree.distance[&v]) / 3.0,
+            );
+            if start < end {
+                events.push((OrderedFloat(start), 1));
+                events.push((OrderedFloat(end), -1));
+            }
+        }
+        // intervals are half open, ends sort before starts at the same distance
+        events.sort_unstable();
+        let mut width: i64 = 0;
+        let mut max = 0;
+        for (_, delta) in events {
+            width += delta;
+            max = max.max(width);
+        }
+        max as usize
+    }
+
+    pub fn skeleton_dimension(&self, samples: usize) -> usize {
+        let n = self.graph.get_num_nodes();
+        if n == 0 {
+            return 0;
+        }
+        (0..samples)
+            .into_par_iter()
+            .map(|_| self.skeleton_width(thread_rng().gen_range(0..n)))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// repeatedly takes the node on most unhit paths
+fn greedy_hitting_set(paths: &[Vec<usize>]) -> Vec<usize> {
+    let mut on_paths = HashMap::<usize, Vec<usize>>::new();
+    for (i, path) in paths.iter().enumerate() {
+        for &u in path {
+            on_paths.entry(u).or_default().push(i);
+        }
+    }
+    let mut count = on_paths
+        .iter()
+        .map(|(&u, p)| (u, p.len()))
+        .collect::<HashMap<_, _>>();
+    let mut hit = vec![false; paths.len()];
+    let mut hitting_set = vec![];
+    while let Some((&u, &c)) = count.iter().max_by_key(|&(_, &c)| c) {
+        if c == 0 {
+            break;
+        }
+        hitting_set.push(u);
+        for &i in &on_paths[&u] {
+            if !hit[i] {
+                hit[i] = true;
+                for v in &paths[i] {
+                    *count.get_mut(v).unwrap() -= 1;
+                }
+            }
+        }
+    }
+    hitting_set
+}
+
+// highway and skeleton dimension of germany and the synthetic models at the same size. radii
+// are fractions of the weighted diameter, results go to output/highway_dimension/{name}
+pub fn compare_models(samples: usize, sources: usize) {
+    let germany = example::geometric_germany();
+    let n = germany.graph.get_num_nodes();
+    let models = [
+        ("germany", germany),
+        ("grid", example::geometric_grid((n as f64).sqrt() as usize)),
+        ("delaunay", delaunay::delaunay(n)),
+        ("gabriel", gabriel_graph::gabriel_graph(n)),
+        (
+            "relative_neighborhood",
+            relative_neighborhood::relative_neighborhood(n),
+        ),
+        ("noise", noise::noise(n)),
+    ];
+    let dir = Config::get().output("highway_dimension");
+
+    for (name, g) in models {
+        let g = g.largest_connected_component();
+        let diameter = g.get_diameter();
+        let mut out = String::new();
+        for k in 3..=7 {
+            let r = diameter / 2f64.powi(k);
+            let hd = g.highway_dimension(r, samples, sources);
+            println!(
+                "{}\t{}: r = {:.3}, highway dimension max {} mean {:.1}",
+                Local::now(),
+                name,
+                r,
+                hd.max,
+                hd.mean
+            );
+            out.push_str(&format!("{} {} {}\n", r, hd.max, hd.mean));
+        }
+        let skeleton = g.skeleton_dimension(samples);
+        println!(
+            "{}\t{}: skeleton dimension {}",
+            Local::now(),
+            name,
+            skeleton
+        );
+        out.push_str(&format!("skeleton {}\n", skeleton));
+        fs::write(dir.join(name), out).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::example::{geometric_grid, geometric_path};
+    use crate::graph::Graph;
+
+    #[test]
+    fn path_dimensions() {
+        let g = geometric_path(100);
+        // the crossing paths are 12 nodes long and start within 20 of the center, three nodes
+        // hit them all, greedy takes at most five
+        let hd = g.highway_dimension(10.0, 20, 1_000);
+        assert!(hd.max <= 5 && hd.hitting_sets.iter().all(|&h| h >= 1));
+        // from the middle both directions are long, from the ends only one
+        assert_eq!(g.skeleton_width(50), 2);
+        assert_eq!(g.skeleton_width(0), 1);
+        assert_eq!(g.skeleton_dimension(10), 2);
+
+        let empty = GeometricGraph::new(Graph::with_node_count(0), vec![]);
+        assert_eq!(empty.highway_dimension(10.0, 20, 1_000).max, 0);
+        assert_eq!(empty.skeleton_dimension(10), 0);
+    }
+
+    #[test]
+    fn star_hitting_set() {
+        // every path between two leaves runs through the center
+        let mut g = Graph::with_node_count(9);
+        let mut positions = vec![Point::new(0.0, 0.0)];
+        for i in 1..9 {
+            g.add_edge(0, i);
+            let angle = i as f64 * std::f64::consts::PI / 4.0;
+            positions.push(Point::new(angle.cos(), angle.sin()));
+        }
+        let g = GeometricGraph::new(g, positions);
+        let paths = g.crossing_paths(0, 1.5, 100);
+        assert!(!paths.is_empty());
+        assert_eq!(greedy_hitting_set(&paths), vec![0]);
+        assert_eq!(g.skeleton_width(0), 8);
+    }
+
+    #[test]
+    fn grid_grows_with_radius() {
+        let g = geometric_grid(30);
+        let small = g.highway_dimension(2.0, 10, 1_000);
+        let large = g.highway_dimension(8.0, 10, 1_000);
+        assert!(small.max >= 1);
+        assert!(large.max >= small.max);
+    }
+}
@@ -162,9 +162,10 @@ pub fn kruskal3d(n: usize) -> (Graph, Vec<Point3D>) {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, path::Path};
+    use std::fs;
 
     use super::*;
+    use crate::dataset::Config;
 
     #[test]
     fn test_get_mst() {
@@ -188,7 +189,8 @@ mod tests {
             Point::new(2.0, 3.0),
         ];
         let mst = get_mst_points(&points);
-        mst.save(Path::new("./output/tmp/kruskal_test")).unwrap();
+        mst.save_routingkit(&Config::get().output("tmp").join("kruskal_test"))
+            .unwrap();
         assert_eq!(mst.graph.get_num_nodes(), 4);
         assert_eq!(mst.graph.get_num_edges(), 3);
     }
@@ -205,7 +207,8 @@ mod tests {
             })
             .collect::<Vec<_>>();
         let mst = get_mst_points(&points);
-        mst.save(Path::new("./output/tmp/kruskal_random")).unwrap();
+        mst.save_routingkit(&Config::get().output("tmp").join("kruskal_random"))
+            .unwrap();
     }
 
     #[test]
@@ -247,7 +250,8 @@ mod tests {
             })
             .collect::<Vec<_>>();
         let mst = get_mst_points(&points);
-        mst.save(Path::new("./output/tmp/kruskal_100k")).unwrap();
+        mst.save_routingkit(&Config::get().output("tmp").join("kruskal_100k"))
+            .unwrap();
     }
 
     #[test]
@@ -261,7 +265,11 @@ mod tests {
                 format!("{} {}\n", i, diam)
             })
             .collect::<String>();
-        fs::write("./output/diam_kruskal_2d", out).unwrap();
+        fs::write(
+            Config::get().output("diameter").join("diam_kruskal_2d"),
+            out,
+        )
+        .unwrap();
     }
 
     #[test]
@@ -275,7 +283,7 @@ mod tests {
     #[test]
     fn recurse_kruskal_3d() {
         let g = kruskal3d(10_000_000).0;
-        g.recurse_diameter(Some(Path::new("./output/diameter/kruskal_3d")));
+        g.recurse_diameter(Some(&Config::get().output("diameter").join("kruskal_3d")));
     }
 
     #[test]
@@ -293,6 +301,10 @@ mod tests {
             })
             .collect::<String>();
 
-        fs::write("./output/diam_kruskal_3d", diams).unwrap();
+        fs::write(
+            Config::get().output("diameter").join("diam_kruskal_3d"),
+            diams,
+        )
+        .unwrap();
     }
 }
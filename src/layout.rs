@@ -0,0 +1,448 @@
+use chrono::Local;
+use geo::Point;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::Graph;
+
+// Multilevel force-directed layout in the spirit of FM^3:
+// the graph is coarsened by repeated matchings, the coarsest graph gets a random layout,
+// and on the way back up every level is refined with a spring embedder whose repulsive
+// forces are approximated with a Barnes-Hut quadtree.
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutParams {
+    // refinement iterations on the coarsest level, finer levels get fewer
+    pub iterations: usize,
+    // stop coarsening once a level has at most this many nodes
+    pub coarsest_size: usize,
+    // Barnes-Hut opening criterion, smaller is more exact
+    pub theta: f64,
+    // natural spring length, the layout is in these units
+    pub edge_length: f64,
+    // pulls every node towards the center so that components don't drift apart
+    pub gravity: f64,
+}
+
+impl Default for LayoutParams {
+    fn default() -> Self {
+        LayoutParams {
+            iterations: 300,
+            coarsest_size: 50,
+            theta: 1.0,
+            edge_length: 1.0,
+            gravity: 0.01,
+        }
+    }
+}
+
+struct Level {
+    adjacency: Vec<Vec<usize>>,
+    weights: Vec<f64>,
+    // maps every node of the finer level to its node on this level
+    parent_of_finer: Vec<usize>,
+}
+
+fn coarsen(adjacency: &[Vec<usize>], weights: &[f64]) -> Level {
+    let n = adjacency.len();
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.shuffle(&mut thread_rng());
+
+    let mut parent = vec![usize::MAX; n];
+    let mut next = 0;
+    for &u in &order {
+        if parent[u] != usize::MAX {
+            continue;
+        }
+        // match with the lightest unmatched neighbor to keep the coarse weights balanced
+        let partner = adjacency[u]
+            .iter()
+            .copied()
+            .filter(|&v| v != u && parent[v] == usize::MAX)
+            .min_by(|&a, &b| weights[a].total_cmp(&weights[b]));
+        parent[u] = next;
+        if let Some(v) = partner {
+            parent[v] = next;
+        }
+        next += 1;
+    }
+
+    let mut coarse_weights = vec![0.0; next];
+    let mut coarse_adjacency = vec![Vec::new(); next];
+    for u in 0..n {
+        coarse_weights[parent[u]] += weights[u];
+        for &v in &adjacency[u] {
+            if parent[u] != parent[v] {
+                coarse_adjacency[parent[u]].push(parent[v]);
+            }
+        }
+    }
+    coarse_adjacency.par_iter_mut().for_each(|neighbors| {
+        neighbors.sort_unstable();
+        neighbors.dedup();
+    });
+
+    Level {
+        adjacency: coarse_adjacency,
+        weights: coarse_weights,
+        parent_of_finer: parent,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QuadNode {
+    center: (f64, f64),
+    half_size: f64,
+    mass: f64,
+    mass_center: (f64, f64),
+    // index of the first of four consecutive children, usize::MAX for leaves
+    children: usize,
+    // bodies of a leaf are bodies[start..end]
+    start: usize,
+    end: usize,
+}
+
+struct QuadTree {
+    nodes: Vec<QuadNode>,
+    bodies: Vec<usize>,
+}
+
+const MAX_LEAF_SIZE: usize = 4;
+const MAX_DEPTH: usize = 32;
+
+impl QuadTree {
+    fn new(positions: &[(f64, f64)], weights: &[f64]) -> Self {
+        let (min_x, min_y, max_x, max_y) = positions.iter().fold(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(a, b, c, d), &(x, y)| (a.min(x), b.min(y), c.max(x), d.max(y)),
+        );
+        let half_size = ((max_x - min_x).max(max_y - min_y) / 2.0).max(1e-9) * 1.0001;
+        let center = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+
+        let mut tree = QuadTree {
+            nodes: Vec::with_capacity(2 * positions.len()),
+            bodies: (0..positions.len()).collect(),
+        };
+        tree.nodes.push(QuadNode {
+            center,
+            half_size,
+            mass: 0.0,
+            mass_center: (0.0, 0.0),
+            children: usize::MAX,
+            start: 0,
+            end: positions.len(),
+        });
+        tree.build(0, 0, positions, weights);
+        tree
+    }
+
+    fn build(&mut self, idx: usize, depth: usize, positions: &[(f64, f64)], weights: &[f64]) {
+        let QuadNode {
+            center,
+            half_size,
+            start,
+            end,
+            ..
+        } = self.nodes[idx];
+
+        if end - start <= MAX_LEAF_SIZE || depth >= MAX_DEPTH {
+            let (mut mass, mut mx, mut my) = (0.0, 0.0, 0.0);
+            for &b in &self.bodies[start..end] {
+                mass += weights[b];
+                mx += weights[b] * positions[b].0;
+                my += weights[b] * positions[b].1;
+            }
+            let node = &mut self.nodes[idx];
+            node.mass = mass;
+            node.mass_center = if mass > 0.0 {
+                (mx / mass, my / mass)
+            } else {
+                center
+            };
+            return;
+        }
+
+        // partition bodies into the quadrants (x < cx, y < cy), (x >= cx, y < cy), ...
+        let quadrant = |b: usize| {
+            let (x, y) = positions[b];
+            (x >= center.0) as usize + 2 * (y >= center.1) as usize
+        };
+        self.bodies[start..end].sort_unstable_by_key(|&b| quadrant(b));
+
+        let first_child = self.nodes.len();
+        self.nodes[idx].children = first_child;
+        let mut offset = start;
+        for q in 0..4 {
+            let count = self.bodies[offset..end]
+                .iter()
+                .take_while(|&&b| quadrant(b) == q)
+                .count();
+            let quarter = half_size / 2.0;
+            let child_center = (
+                center.0 + if q % 2 == 1 { quarter } else { -quarter },
+                center.1 + if q >= 2 { quarter } else { -quarter },
+            );
+            self.nodes.push(QuadNode {
+                center: child_center,
+                half_size: quarter,
+                mass: 0.0,
+                mass_center: child_center,
+                children: usize::MAX,
+                start: offset,
+                end: offset + count,
+            });
+            offset += count;
+        }
+
+        let (mut mass, mut mx, mut my) = (0.0, 0.0, 0.0);
+        for c in first_child..first_child + 4 {
+            self.build(c, depth + 1, positions, weights);
+            let child = self.nodes[c];
+            mass += child.mass;
+            mx += child.mass * child.mass_center.0;
+            my += child.mass * child.mass_center.1;
+        }
+        let node = &mut self.nodes[idx];
+        node.mass = mass;
+        node.mass_center = if mass > 0.0 {
+            (mx / mass, my / mass)
+        } else {
+            center
+        };
+    }
+
+    // repulsive force on body u, with strength k^2 * w_u * w_v / d
+    fn repulsion(
+        &self,
+        u: usize,
+        positions: &[(f64, f64)],
+        weights: &[f64],
+        theta: f64,
+        k: f64,
+    ) -> (f64, f64) {
+        let (px, py) = positions[u];
+        let mut force = (0.0, 0.0);
+        let mut add = |dx: f64, dy: f64, mass: f64| {
+            let dist_2 = (dx * dx + dy * dy).max(1e-9 * k * k);
+            let f = k * k * weights[u] * mass / dist_2;
+            force.0 += dx * f;
+            force.1 += dy * f;
+        };
+
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            if node.mass == 0.0 {
+                continue;
+            }
+            let (dx, dy) = (px - node.mass_center.0, py - node.mass_center.1);
+            let dist = (dx * dx + dy * dy).sqrt();
+
+            if node.children == usize::MAX {
+                for &b in &self.bodies[node.start..node.end] {
+                    if b != u {
+                        add(px - positions[b].0, py - positions[b].1, weights[b]);
+                    }
+                }
+            } else if 2.0 * node.half_size < theta * dist {
+                add(dx, dy, node.mass);
+            } else {
+                stack.extend(node.children..node.children + 4);
+            }
+        }
+
+        force
+    }
+}
+
+fn refine(
+    adjacency: &[Vec<usize>],
+    weights: &[f64],
+    positions: &mut [(f64, f64)],
+    iterations: usize,
+    initial_temperature: f64,
+    params: &LayoutParams,
+) {
+    let k = params.edge_length;
+    let mut temperature = initial_temperature;
+    let cooling = (0.01f64).powf(1.0 / iterations.max(1) as f64);
+
+    for _ in 0..iterations {
+        let tree = QuadTree::new(positions, weights);
+        let (cx, cy) = {
+            let total = weights.iter().sum::<f64>();
+            let (sx, sy) = positions
+                .iter()
+                .zip(weights)
+                .fold((0.0, 0.0), |(sx, sy), (p, w)| (sx + w * p.0, sy + w * p.1));
+            (sx / total, sy / total)
+        };
+
+        let displacement = (0..positions.len())
+            .into_par_iter()
+            .map(|u| {
+                let (px, py) = positions[u];
+                let (mut fx, mut fy) = tree.repulsion(u, positions, weights, params.theta, k);
+                for &v in &adjacency[u] {
+                    let (dx, dy) = (positions[v].0 - px, positions[v].1 - py);
+                    let dist = (dx * dx + dy * dy).sqrt();
+                    // d^2 / k along the edge direction
+                    fx += dx * dist / k;
+                    fy += dy * dist / k;
+                }
+                fx += params.gravity * weights[u] * (cx - px);
+                fy += params.gravity * weights[u] * (cy - py);
+
+                let length = (fx * fx + fy * fy).sqrt();
+                if length > temperature {
+                    (fx * temperature / length, fy * temperature / length)
+                } else {
+                    (fx, fy)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        positions
+            .par_iter_mut()
+            .zip(displacement)
+            .for_each(|(p, (dx, dy))| {
+                p.0 += dx;
+                p.1 += dy;
+            });
+        temperature *= cooling;
+    }
+}
+
+impl Graph {
+    pub fn force_directed_layout(&self, params: &LayoutParams) -> Vec<Point> {
+        let n = self.get_num_nodes();
+        let adjacency = self
+            .data
+            .par_iter()
+            .map(|neighbors| neighbors.iter().copied().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut levels = vec![Level {
+            adjacency,
+            weights: vec![1.0; n],
+            parent_of_finer: Vec::new(),
+        }];
+        loop {
+            let current = levels.last().unwrap();
+            let size = current.adjacency.len();
+            if size <= params.coarsest_size {
+                break;
+            }
+            let coarse = coarsen(&current.adjacency, &current.weights);
+            // stars and other matching-resistant graphs barely shrink, stop there
+            if coarse.adjacency.len() as f64 > 0.95 * size as f64 {
+                break;
+            }
+            levels.push(coarse);
+        }
+        println!(
+            "{}\tLayout hierarchy with {} levels",
+            Local::now(),
+            levels.len()
+        );
+
+        let k = params.edge_length;
+        let coarsest = levels.last().unwrap();
+        let side = (coarsest.adjacency.len() as f64).sqrt().max(1.0) * k;
+        let mut rng = thread_rng();
+        let mut positions = (0..coarsest.adjacency.len())
+            .map(|_| (rng.gen_range(0.0..side), rng.gen_range(0.0..side)))
+            .collect::<Vec<_>>();
+        refine(
+            &coarsest.adjacency,
+            &coarsest.weights,
+            &mut positions,
+            params.iterations,
+            side / 2.0,
+            params,
+        );
+
+        for i in (0..levels.len() - 1).rev() {
+            let coarse = &levels[i + 1];
+            let fine = &levels[i];
+            // place matched nodes next to each other around their coarse node
+            positions = coarse
+                .parent_of_finer
+                .iter()
+                .map(|&p| {
+                    let (x, y) = positions[p];
+                    (
+                        x + rng.gen_range(-0.1..0.1) * k,
+                        y + rng.gen_range(-0.1..0.1) * k,
+                    )
+                })
+                .collect();
+            let iterations = (params.iterations / (levels.len() - i)).max(20);
+            refine(
+                &fine.adjacency,
+                &fine.weights,
+                &mut positions,
+                iterations,
+                2.0 * k,
+                params,
+            );
+        }
+
+        positions
+            .into_iter()
+            .map(|(x, y)| Point::new(x, y))
+            .collect()
+    }
+
+    pub fn to_geometric_graph(&self) -> GeometricGraph {
+        let positions = self.force_directed_layout(&LayoutParams::default());
+        GeometricGraph::new(self.clone(), positions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::{grid, tree};
+
+    #[test]
+    fn layout_grid() {
+        let g = grid::generate_grid(30);
+        let gg = g.to_geometric_graph();
+        assert_eq!(gg.positions.len(), g.get_num_nodes());
+        assert!(gg
+            .positions
+            .iter()
+            .all(|p| p.x().is_finite() && p.y().is_finite()));
+
+        // neighbors should be much closer than random pairs
+        let edges = g.get_directed_edges();
+        let avg_edge = edges
+            .iter()
+            .map(|&(u, v)| gg.euclidean_distance(u, v))
+            .sum::<f64>()
+            / edges.len() as f64;
+        let avg_random = (0..1000)
+            .map(|_| gg.euclidean_distance(g.get_random_node(), g.get_random_node()))
+            .sum::<f64>()
+            / 1000.0;
+        assert!(avg_edge * 5.0 < avg_random);
+    }
+
+    #[test]
+    fn layout_tree_and_single_node() {
+        let g = tree::random_tree(500);
+        let positions = g.force_directed_layout(&LayoutParams::default());
+        assert_eq!(positions.len(), 500);
+
+        let g = Graph::with_node_count(1);
+        assert_eq!(g.force_directed_layout(&LayoutParams::default()).len(), 1);
+    }
+}
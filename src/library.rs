@@ -8,50 +8,21 @@ use std::str::FromStr;
 
 use geo::{Point, Rect};
 use rand::Rng;
+use rayon::prelude::*;
 
-pub fn read_to_usize_vec(file: &Path) -> Vec<usize> {
-    let mut file = File::open(file).unwrap();
-    let mut buffer = Vec::new();
-
-    file.read_to_end(&mut buffer).unwrap();
-    assert!(buffer.len() % std::mem::size_of::<u32>() == 0);
-    let num_elements = buffer.len() / std::mem::size_of::<u32>();
-    let mut vec = Vec::with_capacity(num_elements);
-
-    for chunk in buffer.chunks(std::mem::size_of::<u32>()) {
-        let elem = unsafe { std::ptr::read(chunk.as_ptr() as *const u32) } as usize;
-        vec.push(elem as usize);
-    }
+use crate::dataset::Config;
+use crate::mmap::{MappedSlice, Pod};
 
-    vec
+pub fn read_to_usize_vec(file: &Path) -> Vec<usize> {
+    MappedSlice::<u32>::open(file)
+        .unwrap()
+        .par_iter()
+        .map(|&x| x as usize)
+        .collect()
 }
 
-pub fn read_binary_vec<T: Sized>(file: &Path) -> io::Result<Vec<T>> {
-    let mut file = File::open(file)?;
-    let mut buffer = Vec::new();
-
-    // Read the entire file into the buffer
-    file.read_to_end(&mut buffer)?;
-
-    // Convert the byte buffer into a vector of type T
-    let element_size = std::mem::size_of::<T>();
-    if buffer.len() % element_size != 0 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "File size is not a multiple of element size",
-        ));
-    }
-
-    let num_elements = buffer.len() / element_size;
-    let mut vec: Vec<T> = Vec::with_capacity(num_elements);
-
-    for chunk in buffer.chunks(element_size) {
-        // Safely interpret the bytes as a reference to type T
-        let elem = unsafe { std::ptr::read(chunk.as_ptr() as *const T) };
-        vec.push(elem);
-    }
-
-    Ok(vec)
+pub fn read_binary_vec<T: Pod>(file: &Path) -> io::Result<Vec<T>> {
+    Ok(MappedSlice::<T>::open(file)?.to_vec())
 }
 
 pub fn write_binary_vec<T: Sized>(input: &[T], file: &Path) -> io::Result<()> {
@@ -268,7 +239,7 @@ pub fn write_histogram_to_file(name: &str, bin_edges: &[f64], counts: &[usize])
         .collect::<String>();
 
     fs::write(
-        format!("./output/histogram/{name}"),
+        Config::get().output("histogram").join(name),
         format!("{bin_edges}\n{counts}"),
     )
 }
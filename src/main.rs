@@ -1,13 +1,30 @@
 pub mod bidirectional;
 pub mod cch;
+pub mod centrality;
+pub mod circuity;
+pub mod container;
+pub mod cycle_separator;
+pub mod dataset;
+pub mod edge_cut;
+pub mod fixtures;
+pub mod flow;
 pub mod graph;
+pub mod highway_dimension;
 pub mod kruskal;
+pub mod layout;
 pub mod lca;
 pub mod library;
 pub mod local;
+pub mod mmap;
+pub mod natural_cuts;
 pub mod osm;
+pub mod partition;
+pub mod profile;
 pub mod random_set;
+pub mod refine;
+pub mod render;
 pub mod separator;
+pub mod spectral;
 
 use graph::{
     cbrt_maximal, delaunay, example::*, gabriel_graph, grid, hierachical_delaunay,
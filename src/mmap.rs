@@ -0,0 +1,206 @@
+use hashbrown::HashSet;
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use std::fs::File;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::container::GraphFile;
+use crate::graph::Graph;
+
+/// # Safety
+/// every bit pattern of the right size has to be a valid value
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub fn map_file(file: &Path) -> io::Result<Option<Arc<Mmap>>> {
+    let f = File::open(file)?;
+    if f.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    // the files are only ever written as a whole, never while someone has them mapped
+    Ok(Some(Arc::new(unsafe { Mmap::map(&f)? })))
+}
+
+// zero-copy view of a little endian array inside a memory mapped file
+#[derive(Clone)]
+pub struct MappedSlice<T: Pod> {
+    map: Option<Arc<Mmap>>,
+    offset: usize,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> MappedSlice<T> {
+    // maps a whole file consisting of nothing but the array (RoutingKit layout)
+    pub fn open(file: &Path) -> io::Result<Self> {
+        let map = map_file(file)?;
+        let bytes = map.as_ref().map_or(0, |m| m.len());
+        MappedSlice::new(map, 0, bytes)
+            .map_err(|e| invalid_data(format!("{}: {e}", file.display())))
+    }
+
+    pub fn new(map: Option<Arc<Mmap>>, offset: usize, bytes: usize) -> io::Result<Self> {
+        if cfg!(target_endian = "big") {
+            return Err(invalid_data("binary graphs are little endian".to_string()));
+        }
+        let size = std::mem::size_of::<T>();
+        if !bytes.is_multiple_of(size) {
+            return Err(invalid_data(format!(
+                "{bytes} bytes is not a multiple of the element size {size}"
+            )));
+        }
+        if let Some(m) = &map {
+            if offset + bytes > m.len() {
+                return Err(invalid_data(
+                    "array reaches past the end of the file".to_string(),
+                ));
+            }
+            if !(m.as_ptr() as usize + offset).is_multiple_of(std::mem::align_of::<T>()) {
+                return Err(invalid_data(format!("offset {offset} is misaligned")));
+            }
+        } else if bytes > 0 {
+            return Err(invalid_data("array in an empty file".to_string()));
+        }
+
+        Ok(MappedSlice {
+            map,
+            offset,
+            len: bytes / size,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Pod> Deref for MappedSlice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match &self.map {
+            // alignment and bounds are checked in new
+            Some(m) => unsafe {
+                std::slice::from_raw_parts(m.as_ptr().add(self.offset) as *const T, self.len)
+            },
+            None => &[],
+        }
+    }
+}
+
+// forward star graph directly on top of the arrays, e.g. the mapped first_out/head files
+pub struct CsrGraph<A: Deref<Target = [u32]> = MappedSlice<u32>> {
+    pub first_out: A,
+    pub head: A,
+}
+
+impl CsrGraph {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        CsrGraph::new(
+            MappedSlice::open(&dir.join("first_out"))?,
+            MappedSlice::open(&dir.join("head"))?,
+        )
+    }
+
+    // either a graph file written by Graph::save or a RoutingKit directory, nothing is copied
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            CsrGraph::open(path)
+        } else {
+            Ok(GraphFile::open(path)?.csr)
+        }
+    }
+}
+
+impl<A: Deref<Target = [u32]> + Sync> CsrGraph<A> {
+    pub fn new(first_out: A, head: A) -> io::Result<Self> {
+        let n = first_out.len().saturating_sub(1);
+        if first_out.first().is_some_and(|&f| f != 0)
+            || first_out
+                .last()
+                .map_or(!head.is_empty(), |&l| l as usize != head.len())
+        {
+            return Err(invalid_data("first_out does not span head".to_string()));
+        }
+        if !first_out.par_windows(2).all(|w| w[0] <= w[1]) {
+            return Err(invalid_data("first_out is not sorted".to_string()));
+        }
+        if !head.par_iter().all(|&v| (v as usize) < n) {
+            return Err(invalid_data("head contains an invalid node".to_string()));
+        }
+        Ok(CsrGraph { first_out, head })
+    }
+
+    pub fn get_num_nodes(&self) -> usize {
+        self.first_out.len().saturating_sub(1)
+    }
+
+    pub fn get_num_edges(&self) -> usize {
+        self.head.len()
+    }
+
+    pub fn get_neighbors(&self, u: usize) -> &[u32] {
+        &self.head[self.first_out[u] as usize..self.first_out[u + 1] as usize]
+    }
+
+    pub fn to_graph(&self) -> Graph {
+        let data = (0..self.get_num_nodes())
+            .into_par_iter()
+            .map(|u| {
+                self.get_neighbors(u)
+                    .iter()
+                    .map(|&v| v as usize)
+                    .collect::<HashSet<_>>()
+            })
+            .collect();
+        Graph { data }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::library;
+
+    #[test]
+    fn csr_roundtrip() {
+        let g = Graph::from_edge_list(vec![(0, 1), (1, 2), (2, 0), (2, 3)]);
+        let dir = tempfile::tempdir().unwrap();
+        g.save_routingkit(dir.path()).unwrap();
+
+        let csr = CsrGraph::open(dir.path()).unwrap();
+        assert_eq!(csr.get_num_nodes(), 4);
+        assert_eq!(csr.get_num_edges(), 8);
+        assert_eq!(csr.get_neighbors(3), &[2]);
+        assert_eq!(csr.to_graph().data, g.data);
+
+        let file = dir.path().join("g.graph");
+        g.save(&file).unwrap();
+        assert_eq!(CsrGraph::from_file(&file).unwrap().to_graph().data, g.data);
+        assert_eq!(CsrGraph::from_file(dir.path()).unwrap().get_num_edges(), 8);
+
+        library::write_binary_vec(&[0u32, 2, 1], &dir.path().join("first_out")).unwrap();
+        assert!(CsrGraph::open(dir.path()).is_err());
+    }
+
+    #[test]
+    fn misaligned() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        library::write_binary_vec(&[1u32, 2, 3], file.path()).unwrap();
+        let map = map_file(file.path()).unwrap();
+        assert!(MappedSlice::<u32>::new(map.clone(), 2, 8).is_err());
+        assert_eq!(&*MappedSlice::<u32>::new(map, 4, 8).unwrap(), &[2, 3]);
+        assert!(MappedSlice::<u64>::open(file.path()).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use chrono::Local;
+use hashbrown::{HashMap, HashSet};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::dataset::Config;
+use crate::flow::{FlowNetwork, INFINITE};
+use crate::graph::example;
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::noise;
+use crate::graph::Graph;
+use crate::render::RenderOptions;
+
+// PUNCH (Delling et al. 2011): the bfs tree around a center holds at most max_size nodes, its
+// first max_size / core_ratio nodes are the core
+pub const PUNCH_MAX_SIZE: usize = 4096;
+pub const PUNCH_CORE_RATIO: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct NaturalCuts {
+    // union of the minimum cuts between all cores and their rings, (u, v) with u < v
+    pub cut_edges: HashSet<(usize, usize)>,
+    // connected components after removing the cut edges
+    pub fragment: Vec<usize>,
+    pub fragment_sizes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NaturalCutStats {
+    pub nodes: usize,
+    pub cut_edges: usize,
+    pub fragments: usize,
+    pub largest_fragment: usize,
+    pub mean_fragment_size: f64,
+    // cut edges relative to the boundary a fragment of that size would have in a grid
+    pub cut_per_fragment: f64,
+    // mean length of cut edges relative to the mean edge length, bridges are long
+    pub cut_edge_length_ratio: Option<f64>,
+}
+
+impl Graph {
+    // bfs from center until max_size nodes are found, the ring stays empty if the component fits
+    fn punch_tree(&self, center: usize, max_size: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut seen = HashSet::new();
+        seen.insert(center);
+        let mut tree = vec![];
+        let mut ring = vec![];
+        let mut queue = VecDeque::from([center]);
+        while let Some(u) = queue.pop_front() {
+            if tree.len() == max_size {
+                ring.push(u);
+                ring.extend(queue.drain(..));
+                break;
+            }
+            tree.push(u);
+            for &v in self.get_neighbors(u) {
+                if seen.insert(v) {
+                    queue.push_back(v);
+                }
+            }
+        }
+        (tree, ring)
+    }
+
+    // minimum edge cut between the core (first nodes of the tree) and the ring around the tree
+    fn punch_cut(&self, tree: &[usize], ring: &[usize], core_size: usize) -> Vec<(usize, usize)> {
+        let index = tree
+            .iter()
+            .chain(ring)
+            .enumerate()
+            .map(|(i, &u)| (u, i))
+            .collect::<HashMap<_, _>>();
+        let mut network = FlowNetwork::new(index.len());
+        let s = network.add_node();
+        let t = network.add_node();
+        for (i, &u) in tree.iter().enumerate() {
+            for &v in self.get_neighbors(u) {
+                // tree edges are added from their smaller end, ring edges from the tree side
+                match index.get(&v) {
+                    Some(&j) if j >= tree.len() || u < v => network.add_edge(i, j, 1),
+                    _ => {}
+                }
+            }
+        }
+        for i in 0..core_size {
+            network.add_arc(s, i, INFINITE);
+        }
+        for j in tree.len()..index.len() {
+            network.add_arc(j, t, INFINITE);
+        }
+        network.max_flow(s, t, INFINITE);
+
+        let source_side = network.source_side(s);
+        tree.iter()
+            .enumerate()
+            .filter(|&(i, _)| source_side[i])
+            .flat_map(|(_, &u)| {
+                self.get_neighbors(u)
+                    .iter()
+                    .filter(|&v| index.get(v).is_some_and(|&j| !source_side[j]))
+                    .map(move |&v| (u.min(v), u.max(v)))
+            })
+            .collect()
+    }
+
+    // repeats punch_cut around random centers until every node lies in some core
+    pub fn natural_cuts(&self, max_size: usize, core_ratio: usize) -> NaturalCuts {
+        let n = self.get_num_nodes();
+        let core_size = (max_size / core_ratio).max(1);
+        let mut centers = self.nodes_iter().collect::<Vec<_>>();
+        centers.shuffle(&mut thread_rng());
+
+        let mut covered = vec![false; n];
+        let mut cut_edges = HashSet::new();
+        for (i, center) in centers.into_iter().enumerate() {
+            if covered[center] {
+                continue;
+            }
+            if i % 100_000 == 0 && i > 0 {
+                println!("{}\t{} of {} centers", Local::now(), i, n);
+            }
+            let (tree, ring) = self.punch_tree(center, max_size);
+            if ring.is_empty() {
+                // small components are fragments of their own
+                for u in tree {
+                    covered[u] = true;
+                }
+                continue;
+            }
+            for &u in &tree[..core_size] {
+                covered[u] = true;
+            }
+            cut_edges.extend(self.punch_cut(&tree, &ring, core_size));
+        }
+
+        let (fragment, fragment_sizes) = self.fragments(&cut_edges);
+        NaturalCuts {
+            cut_edges,
+            fragment,
+            fragment_sizes,
+        }
+    }
+
+    fn fragments(&self, cut_edges: &HashSet<(usize, usize)>) -> (Vec<usize>, Vec<usize>) {
+        let mut fragment = vec![usize::MAX; self.get_num_nodes()];
+        let mut sizes = vec![];
+        for s in self.nodes_iter() {
+            if fragment[s] != usize::MAX {
+                continue;
+            }
+            fragment[s] = sizes.len();
+            let mut size = 0;
+            let mut stack = vec![s];
+            while let Some(u) = stack.pop() {
+                size += 1;
+                for &v in self.get_neighbors(u) {
+                    if fragment[v] == usize::MAX && !cut_edges.contains(&(u.min(v), u.max(v))) {
+                        fragment[v] = sizes.len();
+                        stack.push(v);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        (fragment, sizes)
+    }
+}
+
+impl NaturalCuts {
+    // one node per fragment, edge weights are the number of cut edges between two fragments
+    pub fn fragment_graph(&self) -> (Graph, HashMap<(usize, usize), usize>) {
+        let mut weights = HashMap::new();
+        for &(u, v) in &self.cut_edges {
+            let (a, b) = (self.fragment[u], self.fragment[v]);
+            if a != b {
+                *weights.entry((a, b)).or_insert(0) += 1;
+                *weights.entry((b, a)).or_insert(0) += 1;
+            }
+        }
+        let mut g = Graph::with_node_count(self.fragment_sizes.len());
+        for &(a, b) in weights.keys() {
+            g.add_directed_edge(a, b);
+        }
+        (g, weights)
+    }
+
+    pub fn stats(&self, g: Option<&GeometricGraph>) -> NaturalCutStats {
+        let fragments = self.fragment_sizes.len();
+        let nodes = self.fragment.len();
+        let mean_fragment_size = nodes as f64 / fragments as f64;
+        let cut_edge_length_ratio = g.filter(|_| !self.cut_edges.is_empty()).map(|g| {
+            let lengths = g.get_edge_lengths();
+            let mean_length = lengths.values().sum::<f64>() / lengths.len() as f64;
+            let mean_cut = self
+                .cut_edges
+                .iter()
+                .map(|&(u, v)| g.euclidean_distance(u, v))
+                .sum::<f64>()
+                / self.cut_edges.len() as f64;
+            mean_cut / mean_length
+        });
+        NaturalCutStats {
+            nodes,
+            cut_edges: self.cut_edges.len(),
+            fragments,
+            largest_fragment: self.fragment_sizes.iter().copied().max().unwrap_or(0),
+            mean_fragment_size,
+            cut_per_fragment: 2.0 * self.cut_edges.len() as f64
+                / fragments as f64
+                / mean_fragment_size.sqrt(),
+            cut_edge_length_ratio,
+        }
+    }
+}
+
+impl GeometricGraph {
+    pub fn natural_cuts(&self, max_size: usize, core_ratio: usize) -> NaturalCuts {
+        self.graph.natural_cuts(max_size, core_ratio)
+    }
+
+    // the graph with the endpoints of all cut edges highlighted
+    pub fn render_natural_cuts(&self, cuts: &NaturalCuts, file: &Path) -> io::Result<()> {
+        let endpoints = cuts
+            .cut_edges
+            .iter()
+            .flat_map(|&(u, v)| [u, v])
+            .collect::<HashSet<_>>();
+        self.render_png(
+            file,
+            &RenderOptions {
+                highlight: Some(&endpoints),
+                highlight_radius: 1.5,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+// natural cuts of germany and of a noise graph of the same size, fragment sizes are written to
+// output/natural_cuts/{germany,noise} and the cut edges rendered next to them
+pub fn compare_germany_noise() -> io::Result<Vec<(&'static str, NaturalCutStats)>> {
+    let germany = example::geometric_germany();
+    let noise = noise::noise_scales(germany.graph.get_num_nodes(), &noise::NOISE_SCALES);
+    let dir = Config::get().output("natural_cuts");
+
+    let mut res = vec![];
+    for (name, g) in [("germany", germany), ("noise", noise)] {
+        let cuts = g.natural_cuts(PUNCH_MAX_SIZE, PUNCH_CORE_RATIO);
+        let stats = cuts.stats(Some(&g));
+        println!("{}\t{}: {:?}", Local::now(), name, stats);
+
+        fs::write(
+            dir.join(name),
+            cuts.fragment_sizes
+                .iter()
+                .map(|s| format!("{}\n", s))
+                .collect::<String>(),
+        )?;
+        g.render_natural_cuts(&cuts, &dir.join(format!("{name}.png")))?;
+        res.push((name, stats));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::grid;
+
+    // two 10x10 grids joined by a bridge of two edges
+    fn two_grids() -> Graph {
+        let mut g = grid::generate_grid(10);
+        let other = grid::generate_grid(10);
+        g.increase_size_to(200);
+        for (u, v) in other.get_edges() {
+            g.add_directed_edge(u + 100, v + 100);
+        }
+        g.add_edge(9, 100);
+        g.add_edge(19, 110);
+        g
+    }
+
+    #[test]
+    fn bridge_is_cut() {
+        let g = two_grids();
+        let cuts = g.natural_cuts(120, 10);
+        assert!(cuts.cut_edges.contains(&(9, 100)));
+        assert!(cuts.cut_edges.contains(&(19, 110)));
+        assert_ne!(cuts.fragment[0], cuts.fragment[199]);
+        assert_eq!(cuts.fragment_sizes.iter().sum::<usize>(), 200);
+
+        let (fragments, weights) = cuts.fragment_graph();
+        assert_eq!(fragments.get_num_nodes(), cuts.fragment_sizes.len());
+        assert_eq!(weights.values().sum::<usize>(), 2 * cuts.cut_edges.len());
+    }
+
+    #[test]
+    fn small_graph_is_one_fragment() {
+        let g = grid::generate_grid(5);
+        let cuts = g.natural_cuts(100, 10);
+        assert!(cuts.cut_edges.is_empty());
+        assert_eq!(cuts.fragment_sizes, vec![25]);
+        assert_eq!(cuts.stats(None).fragments, 1);
+    }
+}
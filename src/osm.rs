@@ -8,6 +8,7 @@ use std::io::BufReader;
 use std::path::Path;
 
 use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::projection::Crs;
 use crate::graph::Graph;
 
 impl GeometricGraph {
@@ -170,6 +171,7 @@ impl GeometricGraph {
         Ok(GeometricGraph {
             graph: graph_struct,
             positions: final_positions_ordered,
+            crs: Some(Crs::Wgs84),
         })
     }
 }
@@ -0,0 +1,216 @@
+use hashbrown::HashSet;
+use rayon::prelude::*;
+
+use std::fs;
+use std::path::Path;
+
+use crate::graph::Graph;
+use crate::separator::Mode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellStats {
+    pub size: usize,
+    // cell nodes with a neighbor in another cell
+    pub boundary: usize,
+    // edges leaving the cell
+    pub cut_edges: usize,
+    // hop diameter of the largest connected piece of the cell
+    pub diameter: usize,
+    pub components: usize,
+}
+
+impl Graph {
+    // cell index per node. KaHIP modes call kaffpa directly, the native ones bisect recursively,
+    // splitting k into k / 2 and k - k / 2 cells with blocks of matching size
+    pub fn k_way_partition(&self, k: usize, imbalance: f64, mode: Mode) -> Vec<usize> {
+        match mode {
+            Mode::Spectral | Mode::Native => {
+                let mut part = vec![0; self.get_num_nodes()];
+                let nodes = self.nodes_iter().collect::<Vec<_>>();
+                // the imbalance of the levels multiplies up, so each level gets the root of it
+                let levels = k.next_power_of_two().trailing_zeros().max(1);
+                let level_imbalance = (1.0 + imbalance).powf(1.0 / levels as f64) - 1.0;
+                self.recursive_bisection(&nodes, k, 0, level_imbalance, mode, &mut part);
+                part
+            }
+            _ => self.kaffpa_partition(k as i32, imbalance, rand::random(), mode),
+        }
+    }
+
+    fn recursive_bisection(
+        &self,
+        nodes: &[usize],
+        k: usize,
+        first_cell: usize,
+        imbalance: f64,
+        mode: Mode,
+        part: &mut [usize],
+    ) {
+        if k <= 1 || nodes.len() < 2 {
+            for &u in nodes {
+                part[u] = first_cell;
+            }
+            return;
+        }
+        let fraction = (k / 2) as f64 / k as f64;
+        let side = self
+            .induced_subgraph(nodes)
+            .edge_bisection(fraction, imbalance, mode);
+        let (left, right): (Vec<_>, Vec<_>) = nodes.iter().enumerate().partition(|(i, _)| side[*i]);
+        let left = left.into_iter().map(|(_, &u)| u).collect::<Vec<_>>();
+        let right = right.into_iter().map(|(_, &u)| u).collect::<Vec<_>>();
+        self.recursive_bisection(&left, k / 2, first_cell, imbalance, mode, part);
+        self.recursive_bisection(&right, k - k / 2, first_cell + k / 2, imbalance, mode, part);
+    }
+
+    // (size, boundary) per cell, without the diameters of cell_stats
+    pub fn cell_boundaries(&self, part: &[usize]) -> Vec<(usize, usize)> {
+        let k = part.iter().max().map_or(0, |&c| c + 1);
+        let mut cells = vec![(0, 0); k];
+        for u in self.nodes_iter() {
+            cells[part[u]].0 += 1;
+            if self.get_neighbors(u).iter().any(|&v| part[v] != part[u]) {
+                cells[part[u]].1 += 1;
+            }
+        }
+        cells
+    }
+
+    pub fn cell_stats(&self, part: &[usize]) -> Vec<CellStats> {
+        let k = part.iter().max().map_or(0, |&c| c + 1);
+        let mut members = vec![vec![]; k];
+        for u in self.nodes_iter() {
+            members[part[u]].push(u);
+        }
+
+        members
+            .par_iter()
+            .map(|nodes| {
+                let cell = self.induced_subgraph(nodes);
+                let boundary = nodes
+                    .iter()
+                    .filter(|&&u| self.get_neighbors(u).iter().any(|&v| part[v] != part[u]))
+                    .count();
+                let cut_edges = nodes
+                    .iter()
+                    .map(|&u| {
+                        self.get_neighbors(u)
+                            .iter()
+                            .filter(|&&v| part[v] != part[u])
+                            .count()
+                    })
+                    .sum();
+                let (diameter, components) = match cell.get_num_nodes() {
+                    0 => (0, 0),
+                    _ => (
+                        cell.largest_connected_component()
+                            .diameter_ifub()
+                            .unwrap_or(0),
+                        cell.separator_components(&HashSet::new()).1.len(),
+                    ),
+                };
+                CellStats {
+                    size: nodes.len(),
+                    boundary,
+                    cut_edges,
+                    diameter,
+                    components,
+                }
+            })
+            .collect()
+    }
+
+    // (cell size, boundary size) of every cell for k = 2, 4, 8, ... until the cells drop below
+    // min_cell_size nodes, the multi-level counterpart of the (n, s) separator plot
+    pub fn boundary_distribution(
+        &self,
+        mode: Mode,
+        imbalance: f64,
+        min_cell_size: usize,
+        out_file: &Path,
+    ) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        let mut k = 2;
+        while self.get_num_nodes() / k >= min_cell_size {
+            let part = self.k_way_partition(k, imbalance, mode);
+            res.extend(self.cell_boundaries(&part));
+            k *= 2;
+        }
+
+        fs::write(
+            out_file,
+            res.iter()
+                .map(|(n, b)| format!("{} {}\n", n, b))
+                .collect::<String>(),
+        )
+        .unwrap();
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::grid;
+
+    #[test]
+    fn grid_quarters() {
+        let g = grid::generate_grid(16);
+        let part = g.k_way_partition(4, 0.03, Mode::Native);
+        let stats = g.cell_stats(&part);
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats.iter().map(|c| c.size).sum::<usize>(), 256);
+        assert!(stats.iter().all(|c| c.size <= 70 && c.boundary <= c.size));
+        // every cut edge is counted from both of its cells
+        let cut = g
+            .get_edges()
+            .iter()
+            .filter(|&&(u, v)| part[u] != part[v])
+            .count();
+        assert_eq!(stats.iter().map(|c| c.cut_edges).sum::<usize>(), cut);
+        assert_eq!(
+            g.cell_boundaries(&part),
+            stats
+                .iter()
+                .map(|c| (c.size, c.boundary))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn grid_thirds() {
+        let g = grid::generate_grid(18);
+        let part = g.k_way_partition(3, 0.03, Mode::Native);
+        let sizes = g.cell_boundaries(&part);
+        assert_eq!(sizes.len(), 3);
+        // 108 nodes per cell, up to 3% more
+        assert!(sizes.iter().all(|&(n, _)| n <= 111), "{:?}", sizes);
+    }
+
+    #[test]
+    fn single_cell() {
+        let g = grid::generate_grid(5);
+        let stats = g.cell_stats(&[0; 25]);
+        assert_eq!(
+            stats,
+            vec![CellStats {
+                size: 25,
+                boundary: 0,
+                cut_edges: 0,
+                diameter: 8,
+                components: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn distribution_levels() {
+        let g = grid::generate_grid(20);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        // k = 2 and k = 4
+        let dist = g.boundary_distribution(Mode::Native, 0.03, 100, file.path());
+        assert_eq!(dist.len(), 6);
+        let written = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(written.lines().count(), 6);
+    }
+}
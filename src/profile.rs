@@ -0,0 +1,333 @@
+use chrono::Local;
+use hashbrown::HashSet;
+use rayon::prelude::*;
+use serde_json::{json, Value};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::dataset::{Config, Dataset};
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::Graph;
+use crate::separator::Mode;
+
+// dijkstra sources and random targets per source for the circuity estimate
+const CIRCUITY_SOURCES: usize = 32;
+const CIRCUITY_TARGETS: usize = 64;
+// the separator chain stops below this size
+const MIN_SEPARATOR_GRAPH: usize = 100;
+
+// the standard battery of structural statistics, everything except n and m is scale free so
+// profiles of graphs of different size and extent can be compared
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphProfile {
+    pub nodes: usize,
+    pub edges: usize,
+    pub degree_distribution: Vec<f64>,
+    pub meshedness: f64,
+    // deciles 10% .. 90% of the edge lengths divided by the mean edge length
+    pub edge_length_deciles: Vec<f64>,
+    // mean network distance over straight line distance of random pairs
+    pub circuity: f64,
+    pub clustering: f64,
+    // Faces::degree_distribution of the planarized drawing
+    pub face_degrees: Vec<f64>,
+    pub diameter: usize,
+    // hop diameter over sqrt(n), about constant for grid like graphs
+    pub diameter_ratio: f64,
+    // slope of log s over log n along a chain of native separators
+    pub separator_exponent: Option<f64>,
+    // kansky's connectivity indices: cycles over the planar maximum, m / n and m over the
+    // planar maximum
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma: f64,
+}
+
+impl Graph {
+    // mean local clustering coefficient, nodes of degree < 2 count as 0
+    pub fn clustering_coefficient(&self) -> f64 {
+        let sum = self
+            .data
+            .par_iter()
+            .map(|neighbors| {
+                let k = neighbors.len();
+                if k < 2 {
+                    return 0.0;
+                }
+                let links = neighbors
+                    .iter()
+                    .map(|&v| {
+                        self.get_neighbors(v)
+                            .iter()
+                            .filter(|w| neighbors.contains(*w))
+                            .count()
+                    })
+                    .sum::<usize>();
+                links as f64 / (k * (k - 1)) as f64
+            })
+            .sum::<f64>();
+        sum / self.get_num_nodes().max(1) as f64
+    }
+
+    // (n, s) of the separators of the largest remaining piece until it gets small
+    fn separator_chain(&self) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        let mut g = self.largest_connected_component();
+        while g.get_num_nodes() >= MIN_SEPARATOR_GRAPH {
+            let separator = g.get_separator_wrapper(Mode::Native);
+            res.push((g.get_num_nodes(), separator.len()));
+            match g
+                .get_subgraphs(&separator)
+                .into_iter()
+                .max_by_key(|s| s.get_num_nodes())
+            {
+                Some(sub) if sub.get_num_nodes() < g.get_num_nodes() => g = sub,
+                _ => break,
+            }
+        }
+        res
+    }
+}
+
+// least squares slope of y over x
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    let k = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / k;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / k;
+    let var = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum::<f64>();
+    if points.len() < 2 || var == 0.0 {
+        return None;
+    }
+    let cov = points
+        .iter()
+        .map(|p| (p.0 - mean_x) * (p.1 - mean_y))
+        .sum::<f64>();
+    Some(cov / var)
+}
+
+impl GeometricGraph {
+    pub fn profile(&self) -> GraphProfile {
+        let n = self.graph.get_num_nodes();
+        let m = self.graph.get_num_edges();
+
+        let mut lengths = self
+            .graph
+            .get_edges()
+            .into_iter()
+            .filter(|&(u, v)| u < v)
+            .map(|(u, v)| self.euclidean_distance(u, v))
+            .collect::<Vec<_>>();
+        lengths.sort_unstable_by(f64::total_cmp);
+        let mean_length = lengths.iter().sum::<f64>() / lengths.len().max(1) as f64;
+        let edge_length_deciles = (1..10)
+            .map(|i| match lengths.len() {
+                0 => 0.0,
+                len => lengths[i * (len - 1) / 10] / mean_length,
+            })
+            .collect();
+
+        let diameter = self
+            .graph
+            .largest_connected_component()
+            .diameter_ifub()
+            .unwrap_or(0);
+        let components = self.graph.separator_components(&HashSet::new()).1.len();
+        let separator_points = self
+            .graph
+            .separator_chain()
+            .into_iter()
+            .filter(|&(_, s)| s > 0)
+            .map(|(n, s)| ((n as f64).ln(), (s as f64).ln()))
+            .collect::<Vec<_>>();
+
+        let (n_f, m_f) = (n as f64, m as f64);
+        GraphProfile {
+            nodes: n,
+            edges: m,
+            degree_distribution: self.graph.degree_distribution(),
+            meshedness: self.graph.meshedness_coefficient(),
+            edge_length_deciles,
+            circuity: self.mean_circuity(CIRCUITY_SOURCES, CIRCUITY_TARGETS),
+            clustering: self.graph.clustering_coefficient(),
+            face_degrees: self.planarized_faces().degree_distribution(),
+            diameter,
+            diameter_ratio: diameter as f64 / n_f.sqrt(),
+            separator_exponent: slope(&separator_points),
+            alpha: (m_f - n_f + components as f64) / (2.0 * n_f - 5.0),
+            beta: m_f / n_f,
+            gamma: m_f / (3.0 * (n_f - 2.0)),
+        }
+    }
+}
+
+// |a - b| relative to the larger value, in [0, 1] for values of the same sign
+fn relative_difference(a: f64, b: f64) -> f64 {
+    let scale = a.abs().max(b.abs());
+    if scale == 0.0 {
+        0.0
+    } else {
+        ((a - b).abs() / scale).min(1.0)
+    }
+}
+
+// total variation distance, missing entries are 0
+fn distribution_distance(a: &[f64], b: &[f64]) -> f64 {
+    let len = a.len().max(b.len());
+    let at = |d: &[f64], i: usize| d.get(i).copied().unwrap_or(0.0);
+    (0..len).map(|i| (at(a, i) - at(b, i)).abs()).sum::<f64>() / 2.0
+}
+
+impl GraphProfile {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "nodes": self.nodes,
+            "edges": self.edges,
+            "degree_distribution": self.degree_distribution,
+            "meshedness": self.meshedness,
+            "edge_length_deciles": self.edge_length_deciles,
+            "circuity": self.circuity,
+            "clustering": self.clustering,
+            "face_degrees": self.face_degrees,
+            "diameter": self.diameter,
+            "diameter_ratio": self.diameter_ratio,
+            "separator_exponent": self.separator_exponent,
+            "alpha": self.alpha,
+            "beta": self.beta,
+            "gamma": self.gamma,
+        })
+    }
+
+    pub fn from_json(json: &Value) -> Option<GraphProfile> {
+        let float = |key: &str| json.get(key)?.as_f64();
+        let int = |key: &str| json.get(key)?.as_u64().map(|x| x as usize);
+        let floats = |key: &str| {
+            json.get(key)?
+                .as_array()?
+                .iter()
+                .map(Value::as_f64)
+                .collect::<Option<Vec<_>>>()
+        };
+        Some(GraphProfile {
+            nodes: int("nodes")?,
+            edges: int("edges")?,
+            degree_distribution: floats("degree_distribution")?,
+            meshedness: float("meshedness")?,
+            edge_length_deciles: floats("edge_length_deciles")?,
+            circuity: float("circuity")?,
+            clustering: float("clustering")?,
+            face_degrees: floats("face_degrees")?,
+            diameter: int("diameter")?,
+            diameter_ratio: float("diameter_ratio")?,
+            separator_exponent: float("separator_exponent"),
+            alpha: float("alpha")?,
+            beta: float("beta")?,
+            gamma: float("gamma")?,
+        })
+    }
+
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        fs::write(file, serde_json::to_string_pretty(&self.to_json())?)
+    }
+
+    pub fn load(file: &Path) -> io::Result<GraphProfile> {
+        let json = serde_json::from_str(&fs::read_to_string(file)?)?;
+        GraphProfile::from_json(&json)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "incomplete profile"))
+    }
+
+    // mean over all statistics of their difference in [0, 1], 0 for identical profiles. n, m
+    // and the raw diameter are left out, they only say how large the graphs are
+    pub fn distance(&self, other: &GraphProfile) -> f64 {
+        let mut terms = vec![
+            distribution_distance(&self.degree_distribution, &other.degree_distribution),
+            distribution_distance(&self.face_degrees, &other.face_degrees),
+            self.edge_length_deciles
+                .iter()
+                .zip(&other.edge_length_deciles)
+                .map(|(&a, &b)| relative_difference(a, b))
+                .sum::<f64>()
+                / self.edge_length_deciles.len().max(1) as f64,
+        ];
+        for (a, b) in [
+            (self.meshedness, other.meshedness),
+            (self.circuity, other.circuity),
+            (self.clustering, other.clustering),
+            (self.diameter_ratio, other.diameter_ratio),
+            (self.alpha, other.alpha),
+            (self.beta, other.beta),
+            (self.gamma, other.gamma),
+        ] {
+            terms.push(relative_difference(a, b));
+        }
+        if let (Some(a), Some(b)) = (self.separator_exponent, other.separator_exponent) {
+            terms.push(relative_difference(a, b));
+        }
+        terms.iter().sum::<f64>() / terms.len() as f64
+    }
+}
+
+// profile of a dataset, cached as output/profile/{name}.json
+pub fn reference_profile(name: &str) -> io::Result<GraphProfile> {
+    let file = Config::get().output("profile").join(format!("{name}.json"));
+    if let Ok(profile) = GraphProfile::load(&file) {
+        return Ok(profile);
+    }
+    println!("{}\tprofiling {}", Local::now(), name);
+    let profile = Dataset::load_geometric(name)?.profile();
+    profile.save(&file)?;
+    Ok(profile)
+}
+
+// distance of a generated graph to geometric_germany(), lower is more realistic
+pub fn score_against_germany(g: &GeometricGraph) -> io::Result<f64> {
+    let germany = reference_profile("germany")?;
+    let score = g.profile().distance(&germany);
+    println!("{}\tdistance to germany: {:.4}", Local::now(), score);
+    Ok(score)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::graph::delaunay;
+    use crate::graph::example::geometric_grid;
+
+    #[test]
+    fn grid_profile() {
+        let p = geometric_grid(40).profile();
+        assert_eq!((p.nodes, p.edges), (1600, 3120));
+        assert_eq!(p.clustering, 0.0);
+        assert_eq!(p.face_degrees[4], 1.0);
+        assert_eq!(p.edge_length_deciles, vec![1.0; 9]);
+        assert_eq!(p.diameter, 78);
+        assert!(p.circuity >= 1.0 && p.circuity <= 2f64.sqrt() + 1e-9);
+        let exponent = p.separator_exponent.unwrap();
+        assert!(exponent > 0.2 && exponent < 0.8);
+        assert!((p.beta - 1.95).abs() < 1e-9);
+    }
+
+    #[test]
+    fn json_roundtrip_and_distance() {
+        let grid = geometric_grid(20).profile();
+        let json = serde_json::to_string(&grid.to_json()).unwrap();
+        let parsed = GraphProfile::from_json(&serde_json::from_str(&json).unwrap()).unwrap();
+        // floats may lose the last bit in the json text
+        assert_eq!(parsed.face_degrees, grid.face_degrees);
+        assert!(grid.distance(&parsed) < 1e-12);
+
+        let delaunay = delaunay::delaunay(400).profile();
+        let d = grid.distance(&delaunay);
+        assert!(d > 0.0 && d <= 1.0);
+        assert_eq!(d, delaunay.distance(&grid));
+    }
+
+    #[test]
+    fn clustering_of_triangle() {
+        let g = Graph::from_edge_list(vec![(0, 1), (1, 2), (2, 0), (2, 3)]);
+        // 1, 1, 1/3 and 0
+        assert!((g.clustering_coefficient() - 7.0 / 12.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,175 @@
+use hashbrown::{HashMap, HashSet};
+use std::collections::VecDeque;
+
+use crate::flow::{FlowNetwork, INFINITE};
+use crate::graph::Graph;
+use crate::separator::Mode;
+
+impl Graph {
+    // splits the components left by the separator into two sides, largest components first
+    pub fn separator_sides(&self, separator: &HashSet<usize>) -> Vec<Option<bool>> {
+        let (label, sizes) = self.separator_components(separator);
+        let mut by_size = (0..sizes.len()).collect::<Vec<_>>();
+        by_size.sort_unstable_by(|&a, &b| sizes[b].cmp(&sizes[a]));
+        let mut side_of = vec![false; sizes.len()];
+        let mut side_sizes = [0, 0];
+        for c in by_size {
+            let side = side_sizes[1] < side_sizes[0];
+            side_of[c] = side;
+            side_sizes[side as usize] += sizes[c];
+        }
+        label
+            .iter()
+            .map(|&l| (l != usize::MAX).then(|| side_of[l]))
+            .collect()
+    }
+
+    // nodes within `layers` hops of the separator, including the separator itself
+    fn separator_corridor(&self, separator: &HashSet<usize>, layers: usize) -> Vec<usize> {
+        let mut distance = HashMap::new();
+        let mut queue = VecDeque::new();
+        for &s in separator {
+            distance.insert(s, 0);
+            queue.push_back(s);
+        }
+        let mut corridor = vec![];
+        while let Some(u) = queue.pop_front() {
+            corridor.push(u);
+            if distance[&u] == layers {
+                continue;
+            }
+            for &v in self.get_neighbors(u) {
+                if !distance.contains_key(&v) {
+                    distance.insert(v, distance[&u] + 1);
+                    queue.push_back(v);
+                }
+            }
+        }
+        corridor
+    }
+
+    // the minimum vertex cuts between the two sides inside the corridor that lie closest to
+    // either side, both are at most as large as the separator
+    fn corridor_cuts(&self, separator: &HashSet<usize>, layers: usize) -> [HashSet<usize>; 2] {
+        let side = self.separator_sides(separator);
+        let corridor = self.separator_corridor(separator, layers);
+        let index = corridor
+            .iter()
+            .enumerate()
+            .map(|(i, &u)| (u, i))
+            .collect::<HashMap<_, _>>();
+
+        // node i is split into 2i -> 2i + 1 with capacity one
+        let mut network = FlowNetwork::new(2 * corridor.len());
+        let s = network.add_node();
+        let t = network.add_node();
+        for (i, &u) in corridor.iter().enumerate() {
+            network.add_arc(2 * i, 2 * i + 1, 1);
+            for &v in self.get_neighbors(u) {
+                match (index.get(&v), side[v]) {
+                    (Some(&j), _) => network.add_arc(2 * i + 1, 2 * j, INFINITE),
+                    (None, Some(false)) => network.add_arc(s, 2 * i, INFINITE),
+                    (None, Some(true)) => network.add_arc(2 * i + 1, t, INFINITE),
+                    // separator nodes are always in the corridor
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        network.max_flow(s, t, separator.len());
+
+        let source_side = network.source_side(s);
+        let sink_side = network.sink_side(t);
+        let cut = |in_cut: &dyn Fn(usize) -> bool| {
+            (0..corridor.len())
+                .filter(|&i| in_cut(i))
+                .map(|i| corridor[i])
+                .collect::<HashSet<_>>()
+        };
+        [
+            cut(&|i| source_side[2 * i] && !source_side[2 * i + 1]),
+            cut(&|i| sink_side[2 * i + 1] && !sink_side[2 * i]),
+        ]
+    }
+
+    // flow based improvement of a balanced separator in a corridor of `layers` bfs layers around
+    // it. the result is never larger and stays within the balance, otherwise the input is kept
+    pub fn refine_separator(
+        &self,
+        separator: &HashSet<usize>,
+        imbalance: f64,
+        layers: usize,
+    ) -> HashSet<usize> {
+        let mut best = self.minimize_separator(separator, imbalance);
+        if !self.verify_separator(&best, imbalance).is_valid() {
+            return separator.clone();
+        }
+
+        loop {
+            let improved = (1..=layers)
+                .rev()
+                .flat_map(|k| self.corridor_cuts(&best, k))
+                .filter(|cut| cut.len() < best.len())
+                .filter(|cut| self.verify_separator(cut, imbalance).is_valid())
+                .min_by_key(|cut| cut.len());
+            match improved {
+                Some(cut) => best = self.minimize_separator(&cut, imbalance),
+                None => return best,
+            }
+        }
+    }
+
+    // e.g. Mode::Fast followed by refinement instead of Mode::Strong
+    pub fn get_refined_separator(
+        &self,
+        mode: Mode,
+        imbalance: f64,
+        layers: usize,
+    ) -> HashSet<usize> {
+        let separator = self.get_separator(2, imbalance, rand::random(), mode);
+        self.refine_separator(&separator, imbalance, layers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geo::Point;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::graph::{delaunay, grid};
+
+    #[test]
+    fn straighten_grid_separator() {
+        let side = 12;
+        let node = |i: usize, j: usize| i * side + j;
+        let g = grid::generate_grid(side);
+
+        // column 5 with a bump out to column 7 in rows 4 to 7
+        let mut sep = (0..12)
+            .filter(|i| !(4..8).contains(i))
+            .map(|i| node(i, 5))
+            .collect::<HashSet<_>>();
+        sep.extend((4..8).map(|i| node(i, 7)));
+        sep.extend([node(3, 6), node(3, 7), node(8, 6), node(8, 7)]);
+        assert_eq!(sep.len(), 16);
+        assert!(g.verify_separator(&sep, 0.33).is_valid());
+
+        let refined = g.refine_separator(&sep, 0.33, 2);
+        assert_eq!(refined.len(), 12);
+        assert!(g.verify_separator(&refined, 0.33).is_valid());
+    }
+
+    #[test]
+    fn never_worse() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let points = (0..1_000)
+            .map(|_| Point::new(rng.gen(), rng.gen()))
+            .collect::<Vec<_>>();
+        let g = delaunay::delaunay_points(&points).graph;
+        let sep = g.spectral_separator(0.2);
+        let refined = g.refine_separator(&sep, 0.2, 3);
+        assert!(refined.len() <= sep.len());
+        assert!(g.verify_separator(&refined, 0.2).is_valid());
+    }
+}
@@ -0,0 +1,518 @@
+use geo::{Intersects, Point, Rect};
+use hashbrown::{HashMap, HashSet};
+use rayon::prelude::*;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::graph::geometric_graph::GeometricGraph;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+const BLACK: Rgb = Rgb(0, 0, 0);
+const WHITE: Rgb = Rgb(255, 255, 255);
+const GREY: Rgb = Rgb(170, 170, 170);
+const HIGHLIGHT: Rgb = Rgb(228, 26, 28);
+
+// categorical palette, used for road classes and separator levels
+const PALETTE: [Rgb; 8] = [
+    Rgb(228, 26, 28),
+    Rgb(55, 126, 184),
+    Rgb(77, 175, 74),
+    Rgb(152, 78, 163),
+    Rgb(255, 127, 0),
+    Rgb(166, 86, 40),
+    Rgb(247, 129, 191),
+    Rgb(102, 102, 102),
+];
+
+// coarse viridis, interpolated linearly
+const VIRIDIS: [Rgb; 5] = [
+    Rgb(68, 1, 84),
+    Rgb(59, 82, 139),
+    Rgb(33, 145, 140),
+    Rgb(94, 201, 98),
+    Rgb(253, 231, 37),
+];
+
+fn viridis(t: f64) -> Rgb {
+    let t = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f64;
+    let i = (t.floor() as usize).min(VIRIDIS.len() - 2);
+    let f = t - i as f64;
+    let (a, b) = (VIRIDIS[i], VIRIDIS[i + 1]);
+    let lerp = |x: u8, y: u8| (x as f64 + f * (y as f64 - x as f64)).round() as u8;
+    Rgb(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+pub enum EdgeColoring<'a> {
+    Uniform(Rgb),
+    // euclidean length, mapped to viridis relative to the longest visible edge
+    Length,
+    // road class per edge, looked up as (u, v) or (v, u)
+    RoadClass(&'a HashMap<(usize, usize), u8>),
+    // separator level per node (usize::MAX = in no separator), an edge gets the
+    // smallest level of its endpoints
+    SeparatorLevel(&'a [usize]),
+}
+
+pub struct RenderOptions<'a> {
+    pub width: u32,
+    pub height: u32,
+    // only draw what lies inside this box, None = bounding box of the whole graph
+    pub viewport: Option<Rect>,
+    pub coloring: EdgeColoring<'a>,
+    pub highlight: Option<&'a HashSet<usize>>,
+    pub highlight_radius: f64,
+    pub line_width: f64,
+    pub background: Rgb,
+    // snap edges to the pixel grid and drop duplicates, needed for graphs with millions of edges
+    pub level_of_detail: bool,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        RenderOptions {
+            width: 2000,
+            height: 2000,
+            viewport: None,
+            coloring: EdgeColoring::Uniform(BLACK),
+            highlight: None,
+            highlight_radius: 3.0,
+            line_width: 1.0,
+            background: WHITE,
+            level_of_detail: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    from: (f64, f64),
+    to: (f64, f64),
+    color: Rgb,
+}
+
+// maps graph coordinates to pixel coordinates, keeping the aspect ratio and flipping the y axis
+struct Transform {
+    viewport: Rect,
+    scale: f64,
+    offset: (f64, f64),
+    height: f64,
+}
+
+impl Transform {
+    fn new(viewport: Rect, width: u32, height: u32) -> Self {
+        let w = viewport.width().max(f64::EPSILON);
+        let h = viewport.height().max(f64::EPSILON);
+        let scale = (width as f64 / w).min(height as f64 / h);
+        let offset = (
+            (width as f64 - w * scale) / 2.0,
+            (height as f64 - h * scale) / 2.0,
+        );
+        Transform {
+            viewport,
+            scale,
+            offset,
+            height: height as f64,
+        }
+    }
+
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.offset.0 + (x - self.viewport.min().x) * self.scale,
+            self.height - self.offset.1 - (y - self.viewport.min().y) * self.scale,
+        )
+    }
+}
+
+fn bounding_rect(positions: &[Point]) -> Rect {
+    let (min_x, min_y, max_x, max_y) = positions
+        .par_iter()
+        .fold_with(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(a, b, c, d), p| (a.min(p.x()), b.min(p.y()), c.max(p.x()), d.max(p.y())),
+        )
+        .reduce(
+            || {
+                (
+                    f64::INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                )
+            },
+            |x, y| (x.0.min(y.0), x.1.min(y.1), x.2.max(y.2), x.3.max(y.3)),
+        );
+
+    if min_x > max_x {
+        return Rect::new((0.0, 0.0), (1.0, 1.0));
+    }
+    Rect::new((min_x, min_y), (max_x, max_y))
+}
+
+// Liang-Barsky clipping of the segment a-b against the rectangle
+fn clip(rect: &Rect, a: (f64, f64), b: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+
+    for (p, q) in [
+        (-dx, a.0 - rect.min().x),
+        (dx, rect.max().x - a.0),
+        (-dy, a.1 - rect.min().y),
+        (dy, rect.max().y - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+    Some((
+        (a.0 + t0 * dx, a.1 + t0 * dy),
+        (a.0 + t1 * dx, a.1 + t1 * dy),
+    ))
+}
+
+impl GeometricGraph {
+    fn render_segments(&self, options: &RenderOptions) -> (Vec<Segment>, Vec<(f64, f64)>) {
+        let viewport = options
+            .viewport
+            .unwrap_or_else(|| bounding_rect(&self.positions));
+        let transform = Transform::new(viewport, options.width, options.height);
+
+        let visible = self
+            .graph
+            .get_directed_edges()
+            .into_par_iter()
+            .filter_map(|(u, v)| {
+                let (a, b) = (self.positions[u], self.positions[v]);
+                clip(&viewport, a.x_y(), b.x_y()).map(|(a, b)| (u, v, a, b))
+            })
+            .collect::<Vec<_>>();
+
+        let max_length = match options.coloring {
+            EdgeColoring::Length => visible
+                .par_iter()
+                .map(|&(u, v, _, _)| self.euclidean_distance(u, v))
+                .reduce(|| 0.0, f64::max)
+                .max(f64::EPSILON),
+            _ => 1.0,
+        };
+
+        let color = |u: usize, v: usize| match &options.coloring {
+            EdgeColoring::Uniform(c) => *c,
+            EdgeColoring::Length => viridis(self.euclidean_distance(u, v) / max_length),
+            EdgeColoring::RoadClass(classes) => classes
+                .get(&(u, v))
+                .or_else(|| classes.get(&(v, u)))
+                .map(|&c| PALETTE[c as usize % PALETTE.len()])
+                .unwrap_or(GREY),
+            EdgeColoring::SeparatorLevel(levels) => match levels[u].min(levels[v]) {
+                usize::MAX => GREY,
+                level => PALETTE[level % PALETTE.len()],
+            },
+        };
+
+        let mut segments = visible
+            .into_par_iter()
+            .map(|(u, v, a, b)| Segment {
+                from: transform.apply(a),
+                to: transform.apply(b),
+                color: color(u, v),
+            })
+            .collect::<Vec<_>>();
+
+        if options.level_of_detail {
+            let snap = |(x, y): (f64, f64)| (x.round(), y.round());
+            segments = segments
+                .into_par_iter()
+                .map(|s| {
+                    let (from, to) = (snap(s.from), snap(s.to));
+                    // orient canonically so that u-v and v-u collapse into one segment
+                    let (from, to) = if (from.0, from.1) <= (to.0, to.1) {
+                        (from, to)
+                    } else {
+                        (to, from)
+                    };
+                    Segment {
+                        from,
+                        to,
+                        color: s.color,
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut seen = HashSet::with_capacity(segments.len());
+            segments.retain(|s| {
+                seen.insert((
+                    s.from.0 as i64,
+                    s.from.1 as i64,
+                    s.to.0 as i64,
+                    s.to.1 as i64,
+                    s.color,
+                ))
+            });
+        }
+
+        let highlights = options
+            .highlight
+            .map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|&n| self.positions[n])
+                    .filter(|p| viewport.intersects(p))
+                    .map(|p| transform.apply(p.x_y()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        (segments, highlights)
+    }
+
+    pub fn render_svg(&self, file: &Path, options: &RenderOptions) -> io::Result<()> {
+        let (segments, highlights) = self.render_segments(options);
+
+        let mut by_color: HashMap<Rgb, String> = HashMap::new();
+        for s in &segments {
+            let d = by_color.entry(s.color).or_default();
+            write!(
+                d,
+                "M{:.1} {:.1}L{:.1} {:.1}",
+                s.from.0, s.from.1, s.to.0, s.to.1
+            )
+            .unwrap();
+        }
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = options.width,
+            h = options.height
+        );
+        writeln!(
+            svg,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            options.background.hex()
+        )
+        .unwrap();
+        for (color, d) in by_color {
+            writeln!(
+                svg,
+                "<path d=\"{}\" stroke=\"{}\" stroke-width=\"{}\" fill=\"none\" stroke-linecap=\"round\"/>",
+                d,
+                color.hex(),
+                options.line_width
+            )
+            .unwrap();
+        }
+        for (x, y) in highlights {
+            writeln!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\"/>",
+                x,
+                y,
+                options.highlight_radius,
+                HIGHLIGHT.hex()
+            )
+            .unwrap();
+        }
+        svg.push_str("</svg>\n");
+
+        fs::write(file, svg)
+    }
+
+    pub fn render_png(&self, file: &Path, options: &RenderOptions) -> io::Result<()> {
+        let (segments, highlights) = self.render_segments(options);
+        let mut canvas = Canvas::new(options.width, options.height, options.background);
+
+        for s in &segments {
+            canvas.draw_line(s.from, s.to, options.line_width, s.color);
+        }
+        for &center in &highlights {
+            canvas.fill_circle(center, options.highlight_radius, HIGHLIGHT);
+        }
+
+        canvas.save_png(file)
+    }
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, background: Rgb) -> Self {
+        let pixels = (0..width as usize * height as usize)
+            .flat_map(|_| [background.0, background.1, background.2])
+            .collect();
+        Canvas {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let idx = 3 * (y as usize * self.width as usize + x as usize);
+        let alpha = alpha.clamp(0.0, 1.0);
+        for (i, c) in [color.0, color.1, color.2].into_iter().enumerate() {
+            let old = self.pixels[idx + i] as f64;
+            self.pixels[idx + i] = (old + alpha * (c as f64 - old)).round() as u8;
+        }
+    }
+
+    // anti-aliased line: coverage of each pixel by a capsule of the given width
+    fn draw_line(&mut self, a: (f64, f64), b: (f64, f64), width: f64, color: Rgb) {
+        let r = (width / 2.0).max(0.5);
+        let min_x = (a.0.min(b.0) - r - 1.0).floor() as i64;
+        let max_x = (a.0.max(b.0) + r + 1.0).ceil() as i64;
+        let min_y = (a.1.min(b.1) - r - 1.0).floor() as i64;
+        let max_y = (a.1.max(b.1) + r + 1.0).ceil() as i64;
+
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len_2 = dx * dx + dy * dy;
+
+        // walk along the major axis so that long lines only touch pixels close to them
+        let steps = dx.abs().max(dy.abs()).ceil().max(1.0) as i64;
+        let reach = r.ceil() as i64 + 1;
+        let mut visited = HashSet::new();
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let (cx, cy) = ((a.0 + t * dx).round() as i64, (a.1 + t * dy).round() as i64);
+            for x in (cx - reach).max(min_x)..=(cx + reach).min(max_x) {
+                for y in (cy - reach).max(min_y)..=(cy + reach).min(max_y) {
+                    if !visited.insert((x, y)) {
+                        continue;
+                    }
+                    let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                    let t = if len_2 == 0.0 {
+                        0.0
+                    } else {
+                        (((px - a.0) * dx + (py - a.1) * dy) / len_2).clamp(0.0, 1.0)
+                    };
+                    let dist = ((px - a.0 - t * dx).powi(2) + (py - a.1 - t * dy).powi(2)).sqrt();
+                    self.blend(x, y, color, r + 0.5 - dist);
+                }
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, center: (f64, f64), radius: f64, color: Rgb) {
+        let reach = radius.ceil() as i64 + 1;
+        let (cx, cy) = (center.0.floor() as i64, center.1.floor() as i64);
+        for x in cx - reach..=cx + reach {
+            for y in cy - reach..=cy + reach {
+                let dist = ((x as f64 + 0.5 - center.0).powi(2)
+                    + (y as f64 + 0.5 - center.1).powi(2))
+                .sqrt();
+                self.blend(x, y, color, radius + 0.5 - dist);
+            }
+        }
+    }
+
+    fn save_png(&self, file: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(file)?);
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::delaunay;
+    use crate::graph::example::{example1, example_c4};
+
+    #[test]
+    fn svg_contains_all_edges() {
+        let g = example_c4();
+        let f = tempfile::NamedTempFile::new().unwrap();
+        let options = RenderOptions {
+            width: 100,
+            height: 100,
+            ..Default::default()
+        };
+        g.render_svg(f.path(), &options).unwrap();
+        let svg = fs::read_to_string(f.path()).unwrap();
+        assert_eq!(svg.matches('M').count(), 4);
+    }
+
+    #[test]
+    fn viewport_crops_edges() {
+        let g = example1();
+        let sep = [4].into_iter().collect::<HashSet<_>>();
+        let options = RenderOptions {
+            viewport: Some(Rect::new((-0.1, -0.1), (1.1, 1.1))),
+            highlight: Some(&sep),
+            ..Default::default()
+        };
+        let (segments, highlights) = g.render_segments(&options);
+        // only the square 0-1-2-3 and the clipped stub of 3-4 remain
+        assert_eq!(segments.len(), 5);
+        assert!(highlights.is_empty());
+    }
+
+    #[test]
+    fn level_of_detail_merges_segments() {
+        let g = delaunay::delaunay(1000);
+        let options = RenderOptions {
+            width: 10,
+            height: 10,
+            ..Default::default()
+        };
+        let (segments, _) = g.render_segments(&options);
+        assert!(segments.len() < g.graph.get_num_edges() / 2);
+    }
+
+    #[test]
+    fn png_has_requested_size() {
+        let g = example1();
+        let levels = vec![usize::MAX, usize::MAX, usize::MAX, 0, 0, 1, 1, 1, 1];
+        let f = tempfile::NamedTempFile::new().unwrap();
+        let options = RenderOptions {
+            width: 64,
+            height: 32,
+            coloring: EdgeColoring::SeparatorLevel(&levels),
+            ..Default::default()
+        };
+        g.render_png(f.path(), &options).unwrap();
+
+        let decoder = png::Decoder::new(File::open(f.path()).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, 64);
+        assert_eq!(reader.info().height, 32);
+    }
+}
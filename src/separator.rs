@@ -11,10 +11,13 @@ use std::process::{Command, Stdio};
 use std::{fs, ptr};
 use tempfile::{NamedTempFile, TempDir};
 
-use chrono::format;
+use chrono::{format, Local};
 use itertools::{Combinations, Itertools};
 
-use crate::cch::{compute_separator_sizes_from_order, get_top_level_separator};
+use crate::cch::{
+    compute_separator_sizes_from_order, get_top_level_separator, SeparatorTreePolicy,
+};
+use crate::dataset::Config;
 use crate::graph::geometric_graph::GeometricGraph;
 use crate::graph::Graph;
 use crate::library::{optional_append_to_file, read_text_vec, read_to_usize_vec};
@@ -46,6 +49,39 @@ pub enum Mode {
     FastSocial = 3,
     EcoSocial = 4,
     StrongSocial = 5,
+    // not a KaHIP mode, computed natively from the fiedler vector
+    Spectral = 6,
+    // not a KaHIP mode, vertex cover of a native edge bisection, see edge_cut
+    Native = 7,
+}
+
+impl Mode {
+    // the mode as passed to KaHIP, None for the native modes
+    pub fn kahip(self) -> Option<i32> {
+        match self {
+            Mode::Spectral | Mode::Native => None,
+            mode => Some(mode as i32),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SeparatorReport {
+    pub separator_size: usize,
+    // components after removing the separator, largest first
+    pub part_sizes: Vec<usize>,
+    // largest component relative to ceil(n / 2)
+    pub balance: f64,
+    pub balanced: bool,
+    pub is_separator: bool,
+    // separator nodes adjacent to at most one component, each alone could be dropped
+    pub redundant: Vec<usize>,
+}
+
+impl SeparatorReport {
+    pub fn is_valid(&self) -> bool {
+        self.is_separator && self.balanced
+    }
 }
 
 impl Graph {
@@ -56,6 +92,15 @@ impl Graph {
         seed: i32,
         mode: Mode,
     ) -> HashSet<usize> {
+        let kahip_mode = match mode {
+            Mode::Spectral => return self.spectral_separator(imbalance),
+            Mode::Native => {
+                return self.vertex_cover_of_cut(&self.native_edge_bisection(0.5, imbalance))
+            }
+            // everything else is a KaHIP mode
+            mode => mode.kahip().unwrap(),
+        };
+
         let n = self.get_num_nodes() as i32;
         let (xadj, adjncy) = self.get_adjacency_array();
         let mut num_separator_vertices = 0;
@@ -73,18 +118,41 @@ impl Graph {
                 &imbalance,
                 true,
                 seed,
-                mode as i32,
+                kahip_mode,
                 &mut num_separator_vertices,
                 &mut separator_raw,
             );
 
-            return std::slice::from_raw_parts(separator_raw, num_separator_vertices as usize)
+            std::slice::from_raw_parts(separator_raw, num_separator_vertices as usize)
                 .iter()
                 .map(|&x| x as usize)
-                .collect();
+                .collect()
         }
     }
 
+    // get_separator checked with verify_separator and without redundant nodes, the same for
+    // every mode. an invalid separator is reported and minimized anyway
+    pub fn get_minimal_separator(
+        &self,
+        nparts: i32,
+        imbalance: f64,
+        seed: i32,
+        mode: Mode,
+    ) -> HashSet<usize> {
+        let sep = self.get_separator(nparts, imbalance, seed, mode);
+        let report = self.verify_separator(&sep, imbalance);
+        if self.get_num_nodes() > 1 && !report.is_valid() {
+            println!(
+                "{}\tinvalid separator from {:?}: {} nodes, parts {:?}",
+                Local::now(),
+                mode,
+                report.separator_size,
+                report.part_sizes
+            );
+        }
+        self.minimize_separator(&sep, imbalance)
+    }
+
     pub fn get_separator_wrapper(&self, mode: Mode) -> HashSet<usize> {
         self.get_separator(2, 0.33, rand::random(), mode)
     }
@@ -93,6 +161,198 @@ impl Graph {
         self.get_separator_wrapper(mode).len()
     }
 
+    // minimum vertex cover of the edges between the two sides (Koenig's theorem),
+    // i.e. the smallest node separator that can be read off a bipartition
+    pub fn vertex_cover_of_cut(&self, side: &[bool]) -> HashSet<usize> {
+        let cut_neighbors = |u: usize| {
+            self.get_neighbors(u)
+                .iter()
+                .copied()
+                .filter(move |&v| side[v] != side[u])
+        };
+        let left = self
+            .nodes_iter()
+            .filter(|&u| side[u] && cut_neighbors(u).next().is_some())
+            .collect::<Vec<_>>();
+
+        // maximum matching with augmenting paths, found by bfs
+        let mut mate = vec![usize::MAX; self.get_num_nodes()];
+        for &root in &left {
+            let mut parent = HashMap::new();
+            let mut queue = VecDeque::from([root]);
+            let mut free_right = None;
+            while let Some(u) = queue.pop_front() {
+                for v in cut_neighbors(u) {
+                    if parent.contains_key(&v) {
+                        continue;
+                    }
+                    parent.insert(v, u);
+                    if mate[v] == usize::MAX {
+                        free_right = Some(v);
+                        break;
+                    }
+                    queue.push_back(mate[v]);
+                }
+                if free_right.is_some() {
+                    break;
+                }
+            }
+
+            let mut v = match free_right {
+                Some(v) => v,
+                None => continue,
+            };
+            loop {
+                let u = parent[&v];
+                let next = mate[u];
+                mate[u] = v;
+                mate[v] = u;
+                if u == root {
+                    break;
+                }
+                v = next;
+            }
+        }
+
+        // nodes reachable from free left nodes via alternating paths
+        let mut reachable = HashSet::new();
+        let mut stack = left
+            .iter()
+            .copied()
+            .filter(|&u| mate[u] == usize::MAX)
+            .collect::<Vec<_>>();
+        reachable.extend(stack.iter().copied());
+        while let Some(u) = stack.pop() {
+            for v in cut_neighbors(u) {
+                if mate[u] == v || !reachable.insert(v) {
+                    continue;
+                }
+                let w = mate[v];
+                if w != usize::MAX && reachable.insert(w) {
+                    stack.push(w);
+                }
+            }
+        }
+
+        let mut cover = left
+            .iter()
+            .copied()
+            .filter(|u| !reachable.contains(u))
+            .collect::<HashSet<_>>();
+        cover.extend(reachable.iter().copied().filter(|&v| !side[v]));
+        cover
+    }
+
+    // component label per node (usize::MAX on the separator) and the component sizes
+    pub fn separator_components(&self, separator: &HashSet<usize>) -> (Vec<usize>, Vec<usize>) {
+        let mut label = vec![usize::MAX; self.get_num_nodes()];
+        let mut sizes = Vec::new();
+        for start in self.nodes_iter() {
+            if label[start] != usize::MAX || separator.contains(&start) {
+                continue;
+            }
+            label[start] = sizes.len();
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(u) = stack.pop() {
+                size += 1;
+                for &v in self.get_neighbors(u) {
+                    if label[v] == usize::MAX && !separator.contains(&v) {
+                        label[v] = sizes.len();
+                        stack.push(v);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        (label, sizes)
+    }
+
+    pub fn verify_separator(&self, separator: &HashSet<usize>, imbalance: f64) -> SeparatorReport {
+        let n = self.get_num_nodes();
+        let (label, mut part_sizes) = self.separator_components(separator);
+        part_sizes.sort_unstable_by(|a, b| b.cmp(a));
+
+        let redundant = separator
+            .iter()
+            .copied()
+            .filter(|&s| {
+                let mut parts = self
+                    .get_neighbors(s)
+                    .iter()
+                    .map(|&v| label[v])
+                    .filter(|&l| l != usize::MAX);
+                let first = parts.next();
+                parts.all(|l| Some(l) == first)
+            })
+            .collect();
+
+        let largest = part_sizes.first().copied().unwrap_or(0);
+        SeparatorReport {
+            separator_size: separator.len(),
+            balance: largest as f64 / n.div_ceil(2).max(1) as f64,
+            balanced: largest as f64 <= (1.0 + imbalance) * n.div_ceil(2) as f64,
+            is_separator: part_sizes.len() >= 2,
+            part_sizes,
+            redundant,
+        }
+    }
+
+    // greedily moves separator nodes that touch at most one part into that part,
+    // as long as the part stays within the balance constraint
+    pub fn minimize_separator(&self, separator: &HashSet<usize>, imbalance: f64) -> HashSet<usize> {
+        let max_part = ((1.0 + imbalance) * self.get_num_nodes().div_ceil(2) as f64) as usize;
+        let (mut label, mut sizes) = self.separator_components(separator);
+        let mut separator = separator.clone();
+
+        let mut candidates = separator.iter().copied().collect::<Vec<_>>();
+        candidates.sort_unstable();
+        for s in candidates {
+            let parts = self
+                .get_neighbors(s)
+                .iter()
+                .map(|&v| label[v])
+                .filter(|&l| l != usize::MAX)
+                .collect::<HashSet<_>>();
+            match parts.len() {
+                0 => {
+                    label[s] = sizes.len();
+                    sizes.push(1);
+                }
+                1 => {
+                    let part = *parts.iter().next().unwrap();
+                    if sizes[part] + 1 > max_part {
+                        continue;
+                    }
+                    label[s] = part;
+                    sizes[part] += 1;
+                }
+                _ => continue,
+            }
+            separator.remove(&s);
+        }
+        separator
+    }
+
+    pub fn induced_subgraph(&self, nodes: &[usize]) -> Graph {
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, &u)| (u, i))
+            .collect::<HashMap<_, _>>();
+        Graph::new(
+            nodes
+                .iter()
+                .map(|u| {
+                    self.get_neighbors(*u)
+                        .iter()
+                        .filter_map(|v| index.get(v).copied())
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
     pub fn get_subgraphs_map(&self, separator: &HashSet<usize>) -> Vec<HashMap<usize, Vec<usize>>> {
         let mut used = vec![false; self.get_num_nodes()];
         let mut subgraphs = Vec::new();
@@ -229,7 +489,7 @@ impl Graph {
             .arg(tmp_graph.path())
             .arg("reorder_nodes_in_flow_cutter_nested_dissection_order_with_separator_stats")
             .arg(tmp_stats.path())
-            .current_dir("../flow-cutter")
+            .current_dir(&Config::get().flowcutter)
             .spawn()
             .expect("Failed to execute flowcutter")
             .wait()
@@ -240,7 +500,7 @@ impl Graph {
             .iter()
             .map(|(u, v)| format!("{} {}\n", u, v))
             .collect::<String>();
-        fs::write(format!("./output/sep/{}", name), s).unwrap();
+        fs::write(Config::get().output("sep").join(name), s).unwrap();
         sep
     }
 
@@ -252,7 +512,7 @@ impl Graph {
         Command::new("./node_ordering")
             .arg(tmp_graph.path())
             .arg(format!("--output_file={}", tmp_ord.path().display()))
-            .current_dir("./dependencies/KaHIP/deploy")
+            .current_dir(&Config::get().kahip)
             .stdout(Stdio::null())
             .spawn()
             .expect("Failed to execute node_ordering")
@@ -260,7 +520,12 @@ impl Graph {
             .unwrap();
 
         let ord = read_kahip_order_file(tmp_ord.path());
-        compute_separator_sizes_from_order(self, &ord, &Path::new("./output/sep").join(name))
+        compute_separator_sizes_from_order(
+            self,
+            &ord,
+            &SeparatorTreePolicy::default(),
+            &Config::get().output("sep").join(name),
+        )
     }
 
     pub fn metis(&self, name: &str) -> Vec<(usize, usize)> {
@@ -282,7 +547,12 @@ impl Graph {
             .expect("Failed to execute ndmetis");
 
         let ord = read_text_vec::<usize>(&tmp_ord).unwrap();
-        compute_separator_sizes_from_order(self, &ord, &Path::new("./output/sep/").join(name))
+        compute_separator_sizes_from_order(
+            self,
+            &ord,
+            &SeparatorTreePolicy::default(),
+            &Config::get().output("sep").join(name),
+        )
     }
 }
 
@@ -337,26 +607,31 @@ fn get_graph(g_map: &HashMap<usize, Vec<usize>>) -> Graph {
 
 impl GeometricGraph {
     pub fn inertial_flowcutter(&self, name: &str) -> Vec<(usize, usize)> {
-        let g_path = Path::new("./output/graphs").join(name);
-        self.save(&g_path);
+        let g_path = Config::get().output("graphs").join(name);
+        self.save_routingkit(&g_path);
         let ord = get_ord(&g_path, Some(name));
 
-        cch::compute_separator_sizes_from_order(
-            &self.graph,
+        // the order is computed on the saved files anyway, stream them instead of the
+        // in-memory graph
+        let out_file = Config::get().output("sep").join(name);
+        cch::compute_separator_sizes_from_dir(
+            &g_path,
             &ord,
-            &Path::new("./output/sep").join(name),
+            &SeparatorTreePolicy::default(),
+            &out_file,
         )
+        .unwrap()
     }
 
     pub fn ifc_top_level_sep(&self, output: &Path) {
-        let g_path = Path::new("./output/graphs").join(output);
-        self.save(&g_path);
+        let g_path = Config::get().output("graphs").join(output);
+        self.save_routingkit(&g_path);
         let ord = get_ord(&g_path, Some(output.to_str().unwrap()));
 
-        let top_level_sep = get_top_level_separator(&self.graph, &ord);
+        let top_level_sep =
+            get_top_level_separator(&self.graph, &ord, &SeparatorTreePolicy::default());
         library::write_text_vec(&top_level_sep, output);
     }
-
 }
 
 pub fn print_binned_statistic(mut data: Vec<(usize, usize)>, num_bins: usize) {
@@ -389,17 +664,15 @@ pub fn print_binned_statistic(mut data: Vec<(usize, usize)>, num_bins: usize) {
 }
 
 pub fn get_ord(graph: &Path, ord_name: Option<&str>) -> Vec<usize> {
-    let ord_file = match ord_name {
-        Some(name) => Path::new("./output/ord").canonicalize().unwrap().join(name),
-        None => Path::new("output/ord").canonicalize().unwrap().join("tmp"),
-    };
+    let ord_dir = Config::get().output("ord").canonicalize().unwrap();
+    let ord_file = ord_dir.join(ord_name.unwrap_or("tmp"));
     println!("{:?}", ord_file);
 
     Command::new("python3")
         .arg("inertialflowcutter_order.py")
         .arg(graph.canonicalize().unwrap().join(""))
         .arg(&ord_file)
-        .current_dir("../InertialFlowCutter")
+        .current_dir(&Config::get().inertial_flowcutter)
         .spawn()
         .expect("Failed to execute inertialflowcutter_order.py")
         .wait()
@@ -424,6 +697,52 @@ mod tests {
         assert_eq!(s.len(), 2);
     }
 
+    #[test]
+    fn verify_and_minimize() {
+        let g = crate::graph::grid::generate_grid(5);
+        // middle column plus the node right of its top, which makes the top redundant as well
+        let sep = HashSet::from([2, 7, 12, 17, 22, 3]);
+        let report = g.verify_separator(&sep, 0.33);
+        assert!(report.is_valid());
+        assert_eq!(report.part_sizes, vec![10, 9]);
+        let mut redundant = report.redundant.clone();
+        redundant.sort();
+        assert_eq!(redundant, vec![2, 3]);
+
+        let minimal = g.minimize_separator(&sep, 0.33);
+        assert_eq!(minimal.len(), 5);
+        let report = g.verify_separator(&minimal, 0.33);
+        assert!(report.is_valid());
+        assert!(report.redundant.is_empty());
+
+        assert!(!g.verify_separator(&HashSet::new(), 0.33).is_separator);
+    }
+
+    #[test]
+    fn minimal_separator_native_modes() {
+        let g = crate::graph::grid::generate_grid(10);
+        for mode in [Mode::Spectral, Mode::Native] {
+            let sep = g.get_minimal_separator(2, 0.33, 42, mode);
+            let report = g.verify_separator(&sep, 0.33);
+            assert!(report.is_separator);
+            assert!(report.redundant.is_empty());
+        }
+    }
+
+    #[test]
+    fn vertex_cover_of_cut() {
+        // star with center 0 on one side and three leaves on the other
+        let g = Graph::new(vec![vec![1, 2, 3], vec![0, 4], vec![0], vec![0], vec![1]]);
+        let side = vec![true, false, false, false, false];
+        let cover = g.vertex_cover_of_cut(&side);
+        assert_eq!(cover, [0].into_iter().collect());
+
+        // perfect matching between the sides needs one node per edge
+        let g = Graph::from_edge_list(vec![(0, 1), (2, 3), (0, 3)]);
+        let side = vec![true, false, true, false];
+        assert_eq!(g.vertex_cover_of_cut(&side).len(), 2);
+    }
+
     #[test]
     fn test_get_graph() {
         let mut g = HashMap::new();
@@ -0,0 +1,371 @@
+use chrono::Local;
+use geo::Point;
+use hashbrown::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::graph::geometric_graph::GeometricGraph;
+use crate::graph::Graph;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpectralParams {
+    // size of the Krylov space before a restart
+    pub lanczos_steps: usize,
+    pub max_restarts: usize,
+    // stop once ||Lx - λx|| is below this
+    pub tolerance: f64,
+    // seed of the random start vector, the same seed gives the same eigenvectors
+    pub seed: u64,
+}
+
+impl Default for SpectralParams {
+    fn default() -> Self {
+        SpectralParams {
+            lanczos_steps: 60,
+            max_restarts: 200,
+            tolerance: 1e-6,
+            seed: 0,
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.par_iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn axpy(alpha: f64, x: &[f64], y: &mut [f64]) {
+    y.par_iter_mut().zip(x).for_each(|(y, x)| *y += alpha * x);
+}
+
+fn normalize(x: &mut [f64]) -> f64 {
+    let norm = dot(x, x).sqrt();
+    if norm > 0.0 {
+        x.par_iter_mut().for_each(|v| *v /= norm);
+    }
+    norm
+}
+
+fn orthogonalize(x: &mut [f64], basis: &[Vec<f64>]) {
+    for b in basis {
+        let c = dot(x, b);
+        axpy(-c, b, x);
+    }
+}
+
+// eigen decomposition of the symmetric tridiagonal matrix with diagonal d and off-diagonal e
+// (implicit QL with shifts), returns eigenvalues and eigenvectors as columns z[row][col]
+fn tridiagonal_eigen(d: &[f64], e: &[f64]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = d.len();
+    let mut d = d.to_vec();
+    let mut e = e.to_vec();
+    e.resize(n, 0.0);
+    let mut z = vec![vec![0.0; n]; n];
+    for (i, row) in z.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+
+    for l in 0..n {
+        let mut iterations = 0;
+        loop {
+            let mut m = l;
+            while m + 1 < n {
+                let dd = d[m].abs() + d[m + 1].abs();
+                if e[m].abs() <= f64::EPSILON * dd {
+                    break;
+                }
+                m += 1;
+            }
+            if m == l {
+                break;
+            }
+            iterations += 1;
+            if iterations > 60 {
+                break;
+            }
+
+            let mut g = (d[l + 1] - d[l]) / (2.0 * e[l]);
+            let mut r = g.hypot(1.0);
+            g = d[m] - d[l] + e[l] / (g + r.copysign(g));
+            let (mut s, mut c, mut p) = (1.0, 1.0, 0.0);
+            let mut underflow = false;
+
+            for i in (l..m).rev() {
+                let mut f = s * e[i];
+                let b = c * e[i];
+                r = f.hypot(g);
+                e[i + 1] = r;
+                if r == 0.0 {
+                    d[i + 1] -= p;
+                    e[m] = 0.0;
+                    underflow = true;
+                    break;
+                }
+                s = f / r;
+                c = g / r;
+                g = d[i + 1] - p;
+                r = (d[i] - g) * s + 2.0 * c * b;
+                p = s * r;
+                d[i + 1] = g + p;
+                g = c * r - b;
+                for row in z.iter_mut() {
+                    f = row[i + 1];
+                    row[i + 1] = s * row[i] + c * f;
+                    row[i] = c * row[i] - s * f;
+                }
+            }
+            if underflow {
+                continue;
+            }
+            d[l] -= p;
+            e[l] = g;
+            e[m] = 0.0;
+        }
+    }
+
+    (d, z)
+}
+
+struct Laplacian {
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl Laplacian {
+    fn new(g: &Graph) -> Self {
+        Laplacian {
+            adjacency: g
+                .data
+                .par_iter()
+                .map(|neighbors| neighbors.iter().copied().collect())
+                .collect(),
+        }
+    }
+
+    fn apply(&self, x: &[f64]) -> Vec<f64> {
+        self.adjacency
+            .par_iter()
+            .enumerate()
+            .map(|(u, neighbors)| {
+                neighbors.len() as f64 * x[u] - neighbors.iter().map(|&v| x[v]).sum::<f64>()
+            })
+            .collect()
+    }
+
+    // smallest eigenpair of L restricted to the orthogonal complement of `deflate`,
+    // explicitly restarted Lanczos with full reorthogonalization
+    fn smallest_eigenpair(&self, deflate: &[Vec<f64>], params: &SpectralParams) -> (f64, Vec<f64>) {
+        let n = self.adjacency.len();
+        if n == 0 {
+            return (0.0, vec![]);
+        }
+        let mut rng = StdRng::seed_from_u64(params.seed);
+        let mut start = (0..n).map(|_| rng.gen_range(-1.0..1.0)).collect::<Vec<_>>();
+        let mut best = (f64::INFINITY, start.clone());
+
+        for restart in 0..params.max_restarts {
+            orthogonalize(&mut start, deflate);
+            if normalize(&mut start) == 0.0 {
+                break;
+            }
+
+            let mut basis: Vec<Vec<f64>> = vec![start];
+            let mut alpha = Vec::new();
+            let mut beta: Vec<f64> = Vec::new();
+
+            for j in 0..params.lanczos_steps.min(n) {
+                let mut w = self.apply(&basis[j]);
+                let a = dot(&w, &basis[j]);
+                alpha.push(a);
+                axpy(-a, &basis[j], &mut w);
+                if j > 0 {
+                    axpy(-beta[j - 1], &basis[j - 1], &mut w);
+                }
+                orthogonalize(&mut w, &basis);
+                orthogonalize(&mut w, deflate);
+
+                let b = normalize(&mut w);
+                beta.push(b);
+                if b < 1e-12 {
+                    break;
+                }
+                basis.push(w);
+            }
+
+            let k = alpha.len();
+            let (theta, s) = tridiagonal_eigen(&alpha, &beta[..k - 1]);
+            let idx = (0..k)
+                .min_by(|&a, &b| theta[a].total_cmp(&theta[b]))
+                .unwrap();
+
+            let mut y = vec![0.0; n];
+            for (i, v) in basis.iter().take(k).enumerate() {
+                axpy(s[i][idx], v, &mut y);
+            }
+            normalize(&mut y);
+
+            let residual = beta[k - 1] * s[k - 1][idx].abs();
+            best = (theta[idx], y.clone());
+            if residual < params.tolerance {
+                println!(
+                    "{}\tLanczos converged after {} restarts (λ={:.3e})",
+                    Local::now(),
+                    restart,
+                    theta[idx]
+                );
+                break;
+            }
+            start = y;
+        }
+
+        best
+    }
+}
+
+impl Graph {
+    // the k smallest non-trivial eigenpairs of the Laplacian, ordered by eigenvalue,
+    // assumes a connected graph (otherwise the constant vector is not the whole null space)
+    pub fn laplacian_eigenvectors(
+        &self,
+        k: usize,
+        params: &SpectralParams,
+    ) -> Vec<(f64, Vec<f64>)> {
+        // with fewer than two steps the Krylov space is just the start vector
+        assert!(
+            params.lanczos_steps >= 2,
+            "lanczos_steps must be at least 2, got {}",
+            params.lanczos_steps
+        );
+        let n = self.get_num_nodes();
+        let laplacian = Laplacian::new(self);
+        let mut deflate = vec![vec![1.0 / (n as f64).sqrt(); n]];
+        let mut result = Vec::with_capacity(k);
+
+        for _ in 0..k.min(n.saturating_sub(1)) {
+            let (lambda, x) = laplacian.smallest_eigenpair(&deflate, params);
+            deflate.push(x.clone());
+            result.push((lambda, x));
+        }
+
+        result
+    }
+
+    pub fn fiedler_vector(&self) -> Vec<f64> {
+        self.laplacian_eigenvectors(1, &SpectralParams::default())
+            .pop()
+            .map(|(_, x)| x)
+            .unwrap_or_else(|| vec![0.0; self.get_num_nodes()])
+    }
+
+    // positions from the second and third eigenvector, e.g. to run inertial flow on abstract graphs
+    pub fn spectral_coordinates(&self) -> GeometricGraph {
+        let eigen = self.laplacian_eigenvectors(2, &SpectralParams::default());
+        let coordinate = |i: usize, u: usize| eigen.get(i).map(|(_, x)| x[u]).unwrap_or(0.0);
+        let positions = (0..self.get_num_nodes())
+            .map(|u| Point::new(coordinate(0, u), coordinate(1, u)))
+            .collect();
+        GeometricGraph::new(self.clone(), positions)
+    }
+
+    // sweep over the nodes sorted by the fiedler vector and cut where the fewest edges cross,
+    // subject to the first side having at most (1 + imbalance) * fraction * n nodes and the
+    // second (1 + imbalance) * (1 - fraction) * n. returns side[u] == true for the first block
+    pub fn spectral_bisection(&self, fraction: f64, imbalance: f64) -> Vec<bool> {
+        let n = self.get_num_nodes();
+        let fiedler = self.fiedler_vector();
+        let mut order = (0..n).collect::<Vec<_>>();
+        order.par_sort_by(|&a, &b| fiedler[a].total_cmp(&fiedler[b]));
+
+        let max_block = |f: f64| (((1.0 + imbalance) * f * n as f64).ceil() as usize).min(n);
+        let (max_first, max_second) = (max_block(fraction), max_block(1.0 - fraction));
+        let mut in_first = vec![false; n];
+        let mut cut = 0i64;
+        let mut best = (i64::MAX, (fraction * n as f64) as usize);
+
+        for (i, &u) in order.iter().enumerate() {
+            in_first[u] = true;
+            for &v in self.get_neighbors(u) {
+                cut += if in_first[v] { -1 } else { 1 };
+            }
+            let size = i + 1;
+            if size <= max_first && n - size <= max_second && cut < best.0 {
+                best = (cut, size);
+            }
+        }
+
+        let mut side = vec![false; n];
+        for &u in &order[..best.1] {
+            side[u] = true;
+        }
+        side
+    }
+
+    pub fn spectral_separator(&self, imbalance: f64) -> HashSet<usize> {
+        let side = self.spectral_bisection(0.5, imbalance);
+        self.vertex_cover_of_cut(&side)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::grid;
+
+    #[test]
+    fn tridiagonal() {
+        // [[2, 1], [1, 2]] has eigenvalues 1 and 3
+        let (mut values, _) = tridiagonal_eigen(&[2.0, 2.0], &[1.0]);
+        values.sort_by(f64::total_cmp);
+        assert!((values[0] - 1.0).abs() < 1e-10);
+        assert!((values[1] - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn path_fiedler_value() {
+        let n = 20;
+        let g = Graph::from_edge_list((0..n - 1).map(|i| (i, i + 1)).collect());
+        let eigen = g.laplacian_eigenvectors(2, &SpectralParams::default());
+        // eigenvalues of the path laplacian are 2 - 2cos(pi k / n)
+        for (k, (lambda, _)) in eigen.iter().enumerate() {
+            let expected = 2.0 - 2.0 * (std::f64::consts::PI * (k + 1) as f64 / n as f64).cos();
+            assert!((lambda - expected).abs() < 1e-6);
+        }
+
+        // the fiedler vector of a path is monotone
+        let x = &eigen[0].1;
+        let increasing = x.windows(2).all(|w| w[0] <= w[1]);
+        let decreasing = x.windows(2).all(|w| w[0] >= w[1]);
+        assert!(increasing || decreasing);
+    }
+
+    #[test]
+    fn empty_graph() {
+        let g = Graph::with_node_count(0);
+        assert!(g
+            .laplacian_eigenvectors(2, &SpectralParams::default())
+            .is_empty());
+        assert!(g.fiedler_vector().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "lanczos_steps must be at least 2")]
+    fn too_few_lanczos_steps() {
+        let params = SpectralParams {
+            lanczos_steps: 0,
+            ..Default::default()
+        };
+        grid::generate_grid(4).laplacian_eigenvectors(1, &params);
+    }
+
+    #[test]
+    fn grid_bisection() {
+        let g = grid::generate_grid(12);
+        let sep = g.spectral_separator(0.33);
+        assert!(sep.len() <= 13);
+
+        let subgraphs = g.get_subgraphs_map(&sep);
+        assert!(subgraphs.len() >= 2);
+        // the bound of spectral_bisection
+        let max_block = (1.33 * 72.0f64).ceil() as usize;
+        assert!(subgraphs.iter().all(|s| s.len() <= max_block));
+    }
+}